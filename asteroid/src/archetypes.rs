@@ -0,0 +1,394 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use serde::Deserialize;
+
+use vermarine_lib::{
+    shipyard::*,
+    tetra::graphics::Color,
+    physics::{
+        PhysicsBody,
+        CollisionBody,
+        Collider,
+        world::{
+            PhysicsWorld,
+        },
+    },
+    components::{
+        Transform,
+    },
+    rendering::{
+        Sprite,
+    },
+};
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+use crate::{
+    components::*,
+    damage::OnDeath,
+    events::{
+        Events,
+        GameEvent,
+    },
+    patterns::{
+        compile,
+        BulletEmitter,
+        Pattern,
+        Program,
+    },
+    layers,
+    draw_layers,
+    SpriteTextures,
+};
+
+pub const ARCHETYPE_DIR: &str = "assets/archetypes";
+
+/// Shipped with the binary so a missing assets folder still gives a playable game
+const BUILTIN: &[(&str, &str)] = &[
+    ("asteroid", include_str!("../../assets/archetypes/asteroid.ron")),
+    ("spinner", include_str!("../../assets/archetypes/spinner.ron")),
+];
+
+//
+// Definitions, deserialized from `assets/archetypes/<name>.ron`
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Kind {
+    /// Wraps around the arena and splits when shot
+    Asteroid,
+    /// Dies once its health runs out
+    Enemy,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum TextureName {
+    Asteroid,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Layer {
+    Player,
+    Enemy,
+    Asteroid,
+    BulletPlayer,
+    BulletEnemy,
+    Wall,
+}
+
+impl Layer {
+    pub fn bits(self) -> u64 {
+        match self {
+            Layer::Player => layers::PLAYER,
+            Layer::Enemy => layers::ENEMY,
+            Layer::Asteroid => layers::ASTEROID,
+            Layer::BulletPlayer => layers::BULLET_PLAYER,
+            Layer::BulletEnemy => layers::BULLET_ENEMY,
+            Layer::Wall => layers::WALL,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Aim {
+    /// Towards the middle of the arena
+    Center,
+    /// Towards wherever the player is when spawned
+    Player,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct SpriteDef {
+    pub texture: TextureName,
+    pub color: (f32, f32, f32),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ColliderDef {
+    /// Picked uniformly from `(min, max)`
+    pub radius: (f64, f64),
+    pub layer: Layer,
+    #[serde(default)]
+    pub mask: Vec<Layer>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct PhysicsDef {
+    /// Picked uniformly from `(min, max)`
+    #[serde(default)]
+    pub speed: (f64, f64),
+    #[serde(default)]
+    pub accel: f64,
+    #[serde(default)]
+    pub curve: f64,
+    pub aim: Aim,
+    /// Degrees either side of `aim` the starting angle may be off by
+    #[serde(default)]
+    pub spread: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct HealthDef {
+    pub max: i32,
+    #[serde(default)]
+    pub iframes: i32,
+    #[serde(default)]
+    pub flash: Option<(f32, f32, f32)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Archetype {
+    pub name: String,
+    pub kind: Kind,
+    pub sprite: SpriteDef,
+    pub collider: ColliderDef,
+    pub physics: PhysicsDef,
+    /// Anything without health dies to a single hit
+    #[serde(default)]
+    pub health: Option<HealthDef>,
+    /// Bullet pattern program, see `patterns`
+    #[serde(default)]
+    pub firing: Option<Vec<Pattern>>,
+    /// Points for destroying it, `Kind::Asteroid` scores by radius instead
+    #[serde(default)]
+    pub score: u64,
+    /// Defaults to splitting for asteroids and bursting for enemies
+    #[serde(default)]
+    pub death: Option<OnDeath>,
+    /// `firing` compiled once on load, shared by every emitter spawned from this
+    #[serde(skip)]
+    pub program: Option<Arc<Program>>,
+}
+
+impl Archetype {
+    pub fn roll_radius(&self, rand: &mut StdRng) -> f64 {
+        roll(rand, self.collider.radius)
+    }
+
+    /// Compiles `firing` into `program`
+    pub fn compile(mut self) -> Result<Self, String> {
+        self.program = match &self.firing {
+            Some(patterns) => Some(Arc::new(compile(patterns)?)),
+            None => None,
+        };
+        Ok(self)
+    }
+
+    pub fn on_death(&self) -> OnDeath {
+        match (self.death, self.kind) {
+            (Some(death), _) => death,
+            (None, Kind::Asteroid) => OnDeath::Split { factor: 1.5, min_radius: 15.0 },
+            (None, Kind::Enemy) => OnDeath::Burst,
+        }
+    }
+}
+
+pub fn rgb((r, g, b): (f32, f32, f32)) -> Color {
+    Color::rgb(r, g, b)
+}
+
+/// Uniform pick from `(min, max)` that tolerates `min == max`
+pub fn roll(rand: &mut StdRng, (min, max): (f64, f64)) -> f64 {
+    if min < max {
+        rand.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+//
+// Archetypes unique
+
+#[derive(Clone, Debug, Default)]
+pub struct Archetypes {
+    defs: HashMap<String, Archetype>,
+}
+
+impl Archetypes {
+    /// The built in definitions, overridden or extended by every `.ron` file in `dir`.
+    /// Files that fail to parse or whose firing doesn't compile are reported and skipped.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Self {
+        let mut archetypes = Archetypes::default();
+
+        for (name, source) in BUILTIN.iter() {
+            let def = ron::de::from_str::<Archetype>(source)
+                .map_err(|e| e.to_string())
+                .and_then(Archetype::compile);
+
+            match def {
+                Ok(def) => archetypes.insert(def),
+                Err(e) => eprintln!("Built in archetype {} is invalid: {}", name, e),
+            }
+        }
+
+        let entries = match fs::read_dir(dir.as_ref()) {
+            Ok(entries) => entries,
+            Err(_) => return archetypes,
+        };
+
+        for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if path.extension().map_or(true, |ext| ext != "ron") {
+                continue;
+            }
+
+            let def = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|source| ron::de::from_str::<Archetype>(&source).map_err(|e| e.to_string()))
+                .and_then(Archetype::compile);
+
+            match def {
+                Ok(def) => archetypes.insert(def),
+                Err(e) => eprintln!("Skipping archetype {}: {}", path.display(), e),
+            }
+        }
+
+        archetypes
+    }
+
+    /// `def` should already have been through `Archetype::compile`
+    pub fn insert(&mut self, def: Archetype) {
+        self.defs.insert(def.name.clone(), def);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Archetype> {
+        self.defs.get(name)
+    }
+}
+
+/// Builds an entity from `archetype` at (`x`, `y`), `radius` being one from `Archetype::roll_radius`.
+/// Any other random parts of the definition are rolled with the world's `StdRng`.
+pub fn spawn_archetype(all_storages: &AllStorages, archetype: &Archetype, x: f64, y: f64, radius: f64) -> EntityId {
+    let color = rgb(archetype.sprite.color);
+
+    let (entity, angle) = all_storages.run(|
+        textures: UniqueView<SpriteTextures>,
+        mut entities: EntitiesViewMut,
+        mut rand: UniqueViewMut<StdRng>,
+        players: View<Player>,
+        mut physicses: ViewMut<Physics>,
+        mut sprites: ViewMut<Sprite>,
+        mut physics_bodies: ViewMut<PhysicsBody>,
+        mut physics_world: UniqueViewMut<PhysicsWorld>,
+        mut transforms: ViewMut<Transform>,| {
+            physics_world.sync(&mut physics_bodies);
+
+            let transform = Transform::new(x, y);
+
+            let (target_x, target_y) = match archetype.physics.aim {
+                Aim::Player => match (&physics_bodies, &players).iter().with_id().next() {
+                    Some((id, _)) => {
+                        let player = physics_world.transform(id);
+                        (player.x, player.y)
+                    }
+                    _ => (0.0, 0.0),
+                },
+                Aim::Center => (0.0, 0.0),
+            };
+            let mut angle = transform.get_angle_to(target_x, target_y);
+            angle += roll(&mut rand, (-archetype.physics.spread, archetype.physics.spread));
+            let speed = roll(&mut rand, archetype.physics.speed);
+
+            let (texture, draw_layer) = (
+                match archetype.sprite.texture {
+                    TextureName::Asteroid => textures.asteroid,
+                    TextureName::Square => textures.square,
+                },
+                match archetype.kind {
+                    Kind::Asteroid => draw_layers::ASTEROID,
+                    Kind::Enemy => draw_layers::ENEMY,
+                },
+            );
+            let entity = entities.add_entity(
+                (
+                    &mut physicses,
+                    &mut sprites,
+                ),
+                (
+                    Physics {
+                        speed,
+                        angle,
+                        accel: archetype.physics.accel,
+                        curve: archetype.physics.curve,
+                        ..Physics::default()
+                    },
+                    create_sprite(texture, radius, color, draw_layer),
+                ),
+            );
+
+            let mask = archetype.collider.mask.iter().fold(0, |mask, layer| mask | layer.bits());
+            physics_world.create_body(
+                &mut entities,
+                &mut physics_bodies,
+                entity,
+                &mut transforms,
+                transform,
+                CollisionBody::from_sensor(Collider::circle(radius, archetype.collider.layer.bits(), mask)),
+            );
+
+            (entity, angle)
+    });
+
+    all_storages.run(|
+        entities: EntitiesViewMut,
+        mut asteroids: ViewMut<Asteroid>,
+        mut enemies: ViewMut<Enemy>,
+        mut emitters: ViewMut<BulletEmitter>,
+        mut healths: ViewMut<Health>,
+        mut on_deaths: ViewMut<OnDeath>,| {
+            match archetype.kind {
+                Kind::Asteroid => entities.add_component(&mut asteroids, Asteroid {}, entity),
+                Kind::Enemy => entities.add_component(&mut enemies, Enemy { score: archetype.score, color }, entity),
+            }
+
+            let health = match &archetype.health {
+                Some(health) => Health::new(health.max, health.iframes, health.flash.map(rgb)),
+                _ => Health::new(1, 0, None),
+            };
+            entities.add_component(&mut healths, health, entity);
+            entities.add_component(&mut on_deaths, archetype.on_death(), entity);
+
+            if let Some(program) = &archetype.program {
+                entities.add_component(
+                    &mut emitters,
+                    BulletEmitter::new(program.clone(), angle),
+                    entity,
+                );
+            }
+    });
+
+    all_storages.borrow::<UniqueViewMut<Events>>().emit(GameEvent::Spawned { entity, x, y });
+
+    entity
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::create_world;
+
+    #[test]
+    fn builtins_load() {
+        let archetypes = Archetypes::load("no-such-dir");
+        assert!(archetypes.get("asteroid").is_some());
+        assert!(archetypes.get("spinner").map_or(false, |spinner| spinner.program.is_some()));
+    }
+
+    #[test]
+    fn spawning_emits_an_event() {
+        let world = create_world(1);
+        let spinner = Archetypes::load("no-such-dir").get("spinner").unwrap().clone();
+
+        let entity = world.run(|all_storages: AllStoragesViewMut| {
+            spawn_archetype(&all_storages, &spinner, 10.0, -20.0, 20.0)
+        });
+
+        let events = world.borrow::<UniqueView<Events>>();
+        assert!(events.iter().any(|event| *event == GameEvent::Spawned { entity, x: 10.0, y: -20.0 }));
+    }
+}
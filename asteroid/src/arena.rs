@@ -0,0 +1,55 @@
+use serde::Deserialize;
+
+//
+// Arena unique
+//
+// The playfield is centred on the origin. Walls sit just outside it, things spawn and wrap
+// `spawn_margin` past it and anything `cull_margin` past it is deleted.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Arena {
+    pub half_width: f64,
+    pub half_height: f64,
+    pub wall_thickness: f64,
+    pub spawn_margin: f64,
+    pub cull_margin: f64,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            half_width: 1000.0,
+            half_height: 500.0,
+            wall_thickness: 20.0,
+            spawn_margin: 300.0,
+            cull_margin: 1000.0,
+        }
+    }
+}
+
+impl Arena {
+    /// (left, right, top, bottom) of the band things spawn in and wrap around
+    pub fn spawn_bounds(&self) -> (f64, f64, f64, f64) {
+        let w = self.half_width + self.spawn_margin;
+        let h = self.half_height + self.spawn_margin;
+        (-w, w, -h, h)
+    }
+
+    pub fn is_culled(&self, x: f64, y: f64) -> bool {
+        x.abs() > self.half_width + self.cull_margin || y.abs() > self.half_height + self.cull_margin
+    }
+
+    /// (x, y, half width, half height) of the left, right, top and bottom walls
+    pub fn walls(&self) -> [(f64, f64, f64, f64); 4] {
+        let t = self.wall_thickness;
+        let x = self.half_width + t;
+        let y = self.half_height + t;
+
+        [
+            (-x, 0.0, t, y + t),
+            (x, 0.0, t, y + t),
+            (0.0, -y, x + t, t),
+            (0.0, y, x + t, t),
+        ]
+    }
+}
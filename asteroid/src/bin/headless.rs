@@ -0,0 +1,76 @@
+use std::{
+    env,
+    path::PathBuf,
+};
+
+use asteroid::{
+    headless,
+    replay::Replay,
+    random_seed,
+};
+
+const DEFAULT_FRAMES: u64 = 10_000;
+
+fn main() {
+    let mut frames = DEFAULT_FRAMES;
+    let mut seed = None;
+    let mut replay_path = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--frames" => {
+                match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => frames = n,
+                    None => usage_error("--frames expects a number"),
+                }
+            }
+            "--seed" => {
+                match args.next().and_then(|n| n.parse().ok()) {
+                    Some(n) => seed = Some(n),
+                    None => usage_error("--seed expects a number"),
+                }
+            }
+            "--replay" => {
+                match args.next() {
+                    Some(path) => replay_path = Some(PathBuf::from(path)),
+                    None => usage_error("--replay expects a path"),
+                }
+            }
+            _ => usage_error(&format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    let report = match replay_path {
+        // Replays carry their own seed and length
+        Some(path) => {
+            let replay = Replay::load(&path).unwrap_or_else(|e| {
+                eprintln!("Failed to load replay {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+            let world = headless::create_world(replay.seed);
+            headless::simulate(&world, replay.frames.len() as u64, |frame| replay.frame(frame))
+        }
+        None => {
+            let world = headless::create_world(seed.unwrap_or_else(random_seed));
+            headless::simulate(&world, frames, headless::scripted_input)
+        }
+    };
+
+    println!("seed: {}", report.seed);
+    println!("frames simulated: {}", report.frames);
+    match report.died_on_frame {
+        Some(frame) => println!("player died on frame: {}", frame),
+        None => println!("player survived with {} hp", report.hp),
+    }
+    println!("score: {} ({} survival bonus)", report.score.total(), report.score.survival_bonus());
+    println!("asteroids alive: {}", report.asteroids);
+    println!("enemies alive: {}", report.enemies);
+    println!("bullets alive: {}", report.bullets);
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("Usage: headless [--frames N] [--seed N] [--replay PATH]");
+    std::process::exit(1);
+}
@@ -0,0 +1,126 @@
+use serde::Deserialize;
+
+use vermarine_lib::tetra::math::Vec2;
+
+use crate::arena::Arena;
+
+//
+// Camera settings, set per stage with `camera` in its `.ron`
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CameraConfig {
+    /// How far the player can get from the centre of the view before it starts following
+    pub deadzone: f32,
+    /// Fraction of the distance past the deadzone covered each tick, 1 snaps straight to the player
+    pub follow_lerp: f32,
+    pub zoom: f32,
+    /// Largest shake offset in pixels, reached at full trauma
+    pub max_shake: f32,
+    /// Trauma lost per tick
+    pub trauma_decay: f32,
+    /// Trauma added when the player is hit
+    pub hit_trauma: f32,
+    /// Trauma added when something is destroyed
+    pub kill_trauma: f32,
+    /// Extra zoom added when something is destroyed, decaying back to `zoom`
+    pub kill_zoom_pulse: f32,
+    /// Fraction of the zoom pulse kept each tick
+    pub zoom_pulse_decay: f32,
+    /// Keep the view inside the walls, or centred on the arena if it's smaller than the view
+    pub clamp_to_arena: bool,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        CameraConfig {
+            deadzone: 60.0,
+            follow_lerp: 0.15,
+            zoom: 1.0,
+            max_shake: 18.0,
+            trauma_decay: 0.03,
+            hit_trauma: 0.6,
+            kill_trauma: 0.15,
+            kill_zoom_pulse: 0.02,
+            zoom_pulse_decay: 0.9,
+            clamp_to_arena: true,
+        }
+    }
+}
+
+//
+// CameraController unique
+//
+// Works out where `Camera` should be each tick, `move_camera` copies the result across
+#[derive(Clone, Debug, Default)]
+pub struct CameraController {
+    pub config: CameraConfig,
+    /// Where the view is centred before shake is added
+    pub focus: Vec2<f32>,
+    /// 0 to 1, shake grows with its square
+    pub trauma: f32,
+    pub zoom_pulse: f32,
+    /// Ticks since the start, drives the shake
+    time: f32,
+}
+
+impl CameraController {
+    pub fn new(config: CameraConfig) -> Self {
+        CameraController {
+            config,
+            ..CameraController::default()
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).min(1.0);
+    }
+
+    pub fn pulse_zoom(&mut self, amount: f32) {
+        self.zoom_pulse += amount;
+    }
+
+    /// Advances a tick following `target`, returning the camera (position, zoom).
+    /// `viewport` is the size of the view in pixels before zooming.
+    pub fn update(&mut self, target: Vec2<f32>, arena: &Arena, viewport: Vec2<f32>) -> (Vec2<f32>, f32) {
+        let config = self.config;
+        self.time += 1.0;
+
+        let offset = target - self.focus;
+        let distance = offset.magnitude();
+        if distance > config.deadzone {
+            let excess = offset * ((distance - config.deadzone) / distance);
+            self.focus += excess * config.follow_lerp;
+        }
+
+        let zoom = config.zoom * (1.0 + self.zoom_pulse);
+        self.zoom_pulse *= config.zoom_pulse_decay;
+
+        if config.clamp_to_arena {
+            let half_view = viewport / (2.0 * zoom);
+            // Far enough out to still show the walls
+            let walls = arena.wall_thickness * 2.0;
+            self.focus.x = clamp_axis(self.focus.x, (arena.half_width + walls) as f32, half_view.x);
+            self.focus.y = clamp_axis(self.focus.y, (arena.half_height + walls) as f32, half_view.y);
+        }
+
+        // Two out of phase sines give a cheap shake that doesn't touch the game's random numbers
+        let shake = config.max_shake * self.trauma * self.trauma;
+        let jitter = Vec2::new(
+            (self.time * 1.7).sin() * (self.time * 0.63).cos(),
+            (self.time * 1.3 + 1.1).sin() * (self.time * 0.77).cos(),
+        );
+        self.trauma = (self.trauma - config.trauma_decay).max(0.0);
+
+        (self.focus + jitter * shake, zoom)
+    }
+}
+
+/// Keeps a view `half_view` either side of `value` inside `-half_arena..half_arena`
+fn clamp_axis(value: f32, half_arena: f32, half_view: f32) -> f32 {
+    if half_view >= half_arena {
+        0.0
+    } else {
+        value.max(-half_arena + half_view).min(half_arena - half_view)
+    }
+}
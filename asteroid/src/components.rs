@@ -2,8 +2,11 @@ use tetra::graphics::{
     Color
 };
 use tetra::math::Vec2;
+use crate::score;
+use serde::Deserialize;
 use vermarine_lib::{
     *,
+    shipyard::EntityId,
     rendering:: {
         Sprite,
         draw_buffer::{
@@ -47,6 +50,165 @@ impl Health {
     }
 }
 
+//
+// Weapon
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Weapon {
+    /// Ticks between shots
+    pub fire_interval: i32,
+    pub cooldown: i32,
+    /// Degrees the projectiles of one shot are fanned across
+    pub spread: f64,
+    pub projectile_count: u32,
+    pub projectile_speed: f64,
+    pub projectile_radius: f64,
+    pub projectile: ProjectileType,
+}
+
+impl Weapon {
+    pub fn blaster() -> Self {
+        Weapon {
+            fire_interval: 6,
+            cooldown: 0,
+            spread: 0.0,
+            projectile_count: 1,
+            projectile_speed: 15.0,
+            projectile_radius: 20.0,
+            projectile: ProjectileType::player(),
+        }
+    }
+
+    pub fn scatter() -> Self {
+        Weapon {
+            fire_interval: 18,
+            cooldown: 0,
+            spread: 40.0,
+            projectile_count: 5,
+            projectile_speed: 12.0,
+            projectile_radius: 12.0,
+            projectile: ProjectileType::player(),
+        }
+    }
+
+    /// From 0 right after firing to 1 when ready to fire again
+    pub fn readiness(&self) -> f32 {
+        if self.fire_interval <= 0 {
+            return 1.0;
+        }
+        1.0 - self.cooldown as f32 / self.fire_interval as f32
+    }
+}
+
+//
+// Power-ups
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PowerUpKind {
+    SpreadShot,
+    ExtraBounce,
+    Shield,
+    SpeedBoost,
+    HealthRefill,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 5] = [
+        PowerUpKind::SpreadShot,
+        PowerUpKind::ExtraBounce,
+        PowerUpKind::Shield,
+        PowerUpKind::SpeedBoost,
+        PowerUpKind::HealthRefill,
+    ];
+
+    pub fn color(self) -> Color {
+        match self {
+            PowerUpKind::SpreadShot => Color::rgb(0.2, 0.5, 1.0),
+            PowerUpKind::ExtraBounce => Color::rgb(0.7, 0.3, 1.0),
+            PowerUpKind::Shield => Color::rgb(0.0, 1.0, 1.0),
+            PowerUpKind::SpeedBoost => Color::rgb(1.0, 0.9, 0.0),
+            PowerUpKind::HealthRefill => Color::rgb(1.0, 0.4, 0.6),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerUp {
+    pub kind: PowerUpKind,
+}
+
+//
+// Timed effects
+//
+// Power-up effects on the player and the ticks each has left
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimedEffects {
+    pub effects: Vec<(PowerUpKind, i32)>,
+}
+
+impl TimedEffects {
+    pub fn has(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|(k, _)| *k == kind)
+    }
+
+    /// Starts `kind` or refreshes it if it's already running
+    pub fn add(&mut self, kind: PowerUpKind, ticks: i32) {
+        match self.effects.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, remaining)) => *remaining = ticks,
+            None => self.effects.push((kind, ticks)),
+        }
+    }
+
+    /// Counts every effect down a tick, returning the ones that ran out
+    pub fn tick(&mut self) -> Vec<PowerUpKind> {
+        let mut expired = vec![];
+        for (kind, remaining) in self.effects.iter_mut() {
+            *remaining -= 1;
+            if *remaining <= 0 {
+                expired.push(*kind);
+            }
+        }
+        self.effects.retain(|(_, remaining)| *remaining > 0);
+        expired
+    }
+}
+
+//
+// Score
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub points: u64,
+    pub frames_survived: u64,
+    pub asteroids_split: u32,
+    pub asteroids_destroyed: u32,
+    pub enemies_destroyed: u32,
+}
+
+impl Score {
+    pub fn add_asteroid_split(&mut self, radius: f64) {
+        self.asteroids_split += 1;
+        self.points += (radius * score::SPLIT_PER_RADIUS) as u64;
+    }
+
+    pub fn add_asteroid_destroyed(&mut self, radius: f64) {
+        self.asteroids_destroyed += 1;
+        self.points += (radius * score::DESTROY_PER_RADIUS) as u64;
+    }
+
+    pub fn add_enemy_destroyed(&mut self, points: u64) {
+        self.enemies_destroyed += 1;
+        self.points += points;
+    }
+
+    pub fn survival_bonus(&self) -> u64 {
+        self.frames_survived / score::SURVIVAL_INTERVAL * score::SURVIVAL_BONUS
+    }
+
+    pub fn total(&self) -> u64 {
+        self.points + self.survival_bonus()
+    }
+}
+
 //
 // sprite creator
 
@@ -90,11 +252,91 @@ impl Default for Physics {
 }
 
 //
-// Spinner
+// Player movement
+//
+// How the player's ship handles. Input picks a direction, this decides how quickly the ship gets
+// going, how quickly it stops and how fast it can go.
 #[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Spinner {
-    pub angle: f64,
-    pub cooldown: i32,
+pub struct PlayerMovement {
+    /// Pixels per tick the velocity can change by while steering
+    pub accel: f64,
+    /// Pixels per tick the speed drops by with no input
+    pub friction: f64,
+    pub max_speed: f64,
+    pub velocity: Vec2<f64>,
+}
+
+impl Default for PlayerMovement {
+    fn default() -> Self {
+        PlayerMovement {
+            accel: 1.0,
+            friction: 0.6,
+            max_speed: 5.0,
+            velocity: Vec2::zero(),
+        }
+    }
+}
+
+impl PlayerMovement {
+    /// Advances a tick steering towards `wish`, returning how far to move.
+    /// `wish` is clamped to a length of 1, so diagonals are no faster and a half pushed stick
+    /// heads for half speed. `speed_scale` multiplies `max_speed`.
+    pub fn update(&mut self, wish: Vec2<f64>, speed_scale: f64) -> Vec2<f64> {
+        let mut wish = wish;
+        if wish.magnitude() > 1.0 {
+            wish.normalize();
+        }
+
+        if wish == Vec2::zero() {
+            let speed = self.velocity.magnitude();
+            if speed <= self.friction {
+                self.velocity = Vec2::zero();
+            } else {
+                self.velocity *= (speed - self.friction) / speed;
+            }
+        } else {
+            let change = wish * self.max_speed * speed_scale - self.velocity;
+            let distance = change.magnitude();
+            if distance <= self.accel {
+                self.velocity += change;
+            } else {
+                self.velocity += change * (self.accel / distance);
+            }
+        }
+
+        self.velocity
+    }
+}
+
+//
+// Enemy
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Enemy {
+    pub score: u64,
+    /// Colour to go back to once a hit flash is over
+    pub color: Color,
+}
+
+
+//
+// Fade
+//
+// Non-colliding sprite that scales by `grow` and fades out over `max_life` frames before being deleted
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fade {
+    pub life: i32,
+    pub max_life: i32,
+    pub grow: f32,
+}
+
+impl Fade {
+    pub fn new(life: i32, grow: f32) -> Self {
+        Fade {
+            life,
+            max_life: life,
+            grow,
+        }
+    }
 }
 
 //
@@ -114,16 +356,95 @@ pub enum Team {
     Ast,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// How a kind of bullet behaves when it hits walls and targets
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ProjectileType {
+    /// The bullet is destroyed on this many wall hits, 0 means it's destroyed on touching a wall without bouncing
+    pub bounce_limit: u32,
+    /// Fraction of its speed a bullet keeps after bouncing
+    pub bounce_speed: f64,
+    /// Whether `Physics::curve` carries on after a bounce
+    pub keep_curve: bool,
+    /// Whether `Physics::accel` carries on after a bounce
+    pub keep_accel: bool,
+    /// Targets the bullet passes through before being used up
+    pub pierce: u32,
+}
+
+impl Default for ProjectileType {
+    fn default() -> Self {
+        ProjectileType {
+            bounce_limit: 0,
+            bounce_speed: 1.0,
+            keep_curve: false,
+            keep_accel: false,
+            pierce: 0,
+        }
+    }
+}
+
+impl ProjectileType {
+    pub fn player() -> Self {
+        ProjectileType {
+            bounce_limit: 3,
+            ..ProjectileType::default()
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bullet {
     pub team: Team,
     pub bounces: u32,
     pub bounce_limit: u32,
+    /// Targets it can still pass through
+    pub pierce: u32,
+    /// Everything it's hit, so a piercing bullet only hits each target once however long it overlaps
+    pub hits: Vec<EntityId>,
+    /// Set by the hit that uses it up, it hits nothing else while waiting to be deleted
+    pub used_up: bool,
+    pub projectile: ProjectileType,
 }
 
 impl Bullet {
-    pub fn new(team: Team) -> Self {
-        Bullet { team: team, bounces: 0, bounce_limit: 3, }
+    pub fn new(team: Team, projectile: ProjectileType) -> Self {
+        Bullet {
+            team: team,
+            bounces: 0,
+            bounce_limit: projectile.bounce_limit,
+            pierce: projectile.pierce,
+            hits: vec![],
+            used_up: false,
+            projectile: projectile,
+        }
+    }
+
+    /// Bouncing bullets are moved by `move_bullets` with a solid collider,
+    /// others are moved by `apply_physics` with a sensor and destroyed on touching a wall
+    pub fn bounces(&self) -> bool {
+        self.bounce_limit > 0
+    }
+
+    /// Records hitting `target`. `None` if it was already hit or the bullet is already used up,
+    /// otherwise whether this hit used it up.
+    pub fn hit(&mut self, target: EntityId) -> Option<bool> {
+        if self.used_up || self.hits.contains(&target) {
+            return None;
+        }
+        self.hits.push(target);
+
+        if self.pierce > 0 {
+            self.pierce -= 1;
+        } else {
+            self.used_up = true;
+        }
+        Some(self.used_up)
+    }
+
+    pub fn with_extra_bounces(mut self, extra: u32) -> Self {
+        self.bounce_limit += extra;
+        self
     }
 }
 
@@ -135,3 +456,72 @@ pub struct Player;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Asteroid;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settle(movement: &mut PlayerMovement, wish: Vec2<f64>) -> Vec2<f64> {
+        for _ in 0..100 {
+            movement.update(wish, 1.0);
+        }
+        movement.velocity
+    }
+
+    #[test]
+    fn diagonals_are_no_faster() {
+        let straight = settle(&mut PlayerMovement::default(), Vec2::new(1.0, 0.0));
+        let diagonal = settle(&mut PlayerMovement::default(), Vec2::new(1.0, -1.0));
+
+        let max_speed = PlayerMovement::default().max_speed;
+        assert!((straight.magnitude() - max_speed).abs() < 1e-9);
+        assert!((diagonal.magnitude() - max_speed).abs() < 1e-9);
+    }
+
+    #[test]
+    fn partial_wish_moves_part_speed() {
+        let velocity = settle(&mut PlayerMovement::default(), Vec2::new(0.5, 0.0));
+        assert!((velocity.x - PlayerMovement::default().max_speed * 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn friction_stops_exactly() {
+        let mut movement = PlayerMovement::default();
+        movement.velocity = Vec2::new(3.0, -4.0);
+
+        let mut ticks = 0;
+        while movement.velocity != Vec2::zero() {
+            movement.update(Vec2::zero(), 1.0);
+            ticks += 1;
+            assert!(ticks <= 10, "still moving at {:?}", movement.velocity);
+        }
+
+        // 5 pixels per tick lost at 0.6 a tick
+        assert_eq!(ticks, 9);
+    }
+
+    #[test]
+    fn accel_limits_change_per_tick() {
+        let mut movement = PlayerMovement::default();
+        let mut previous = movement.velocity;
+
+        for _ in 0..10 {
+            let velocity = movement.update(Vec2::new(-1.0, 1.0), 1.0);
+            assert!((velocity - previous).magnitude() <= movement.accel + 1e-9);
+            previous = velocity;
+        }
+
+        // Turning round is limited the same way
+        let velocity = movement.update(Vec2::new(1.0, 0.0), 1.0);
+        assert!((velocity - previous).magnitude() <= movement.accel + 1e-9);
+    }
+
+    #[test]
+    fn speed_scale_raises_max_speed() {
+        let mut movement = PlayerMovement::default();
+        for _ in 0..100 {
+            movement.update(Vec2::new(0.0, 1.0), 1.5);
+        }
+        assert!((movement.velocity.y - movement.max_speed * 1.5).abs() < 1e-9);
+    }
+}
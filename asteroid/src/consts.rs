@@ -5,17 +5,42 @@ pub mod layers {
     pub const BULLET_PLAYER: u64 = 1 << 3;
     pub const BULLET_ENEMY: u64 = 1 << 4;
     pub const WALL: u64 = 1 << 5;
+    pub const POWERUP: u64 = 1 << 6;
 }
 
 pub mod draw_layers {
     pub const ASTEROID: f32 = 2.0;
     pub const WALL: f32 = 1.0;
+    pub const POWERUP: f32 = 0.5;
     pub const PLAYER: f32 = 0.0;
     pub const ENEMY: f32 = -1.0;
+    pub const PARTICLE: f32 = -1.5;
     pub const BULLET: f32 = -2.0; 
 }
 
 pub mod textures {
     pub const ASTEROID: &'static str = "asteroid";
     pub const SQUARE: &'static str = "square";
-}
\ No newline at end of file
+}
+
+pub mod score {
+    /// Points per unit of radius for shrinking an asteroid, so big hits are worth more
+    pub const SPLIT_PER_RADIUS: f64 = 1.0;
+    /// Points per unit of radius for finishing an asteroid off
+    pub const DESTROY_PER_RADIUS: f64 = 3.0;
+    /// Frames of survival per `SURVIVAL_BONUS` points
+    pub const SURVIVAL_INTERVAL: u64 = 60;
+    pub const SURVIVAL_BONUS: u64 = 10;
+}
+
+pub mod powerups {
+    pub const DROP_CHANCE_ASTEROID: f64 = 0.08;
+    pub const DROP_CHANCE_ENEMY: f64 = 0.5;
+    /// Ticks a dropped power-up waits to be picked up
+    pub const LIFETIME: i32 = 600;
+    /// Ticks a timed effect lasts once picked up
+    pub const DURATION: i32 = 600;
+    pub const RADIUS: f64 = 12.0;
+    pub const SPEED_BOOST: f64 = 1.5;
+    pub const EXTRA_BOUNCES: u32 = 2;
+}
@@ -0,0 +1,398 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use vermarine_lib::tetra::input::{
+    self,
+    GamepadAxis,
+    GamepadButton,
+    InputContext,
+    Key,
+    MouseButton,
+};
+
+/// Only the first connected gamepad is read
+const GAMEPAD: usize = 0;
+
+//
+// Actions
+//
+// Everything the game reads from the keyboard and mouse goes through one of these,
+// so they can be rebound in `controls.ron` or from the options menu.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Pause,
+    Confirm,
+}
+
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Pause,
+        Action::Confirm,
+    ];
+}
+
+/// Keys and buttons are stored by name, see `KEYS`, `MOUSE_BUTTONS` and `GAMEPAD_BUTTONS`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Binding {
+    Key(String),
+    Mouse(String),
+    Gamepad(String),
+}
+
+/// Where movement and aiming come from. Bindings work with either.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum InputDevice {
+    /// Move with the movement actions, aim at the mouse and fire with `Action::Fire`
+    KeyboardMouse,
+    /// Move with the left stick, aim and fire by pushing the right stick
+    Gamepad,
+}
+
+fn default_device() -> InputDevice { InputDevice::KeyboardMouse }
+fn default_deadzone() -> f64 { 0.2 }
+
+/// This frame's twin-stick input, with the deadzone taken out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sticks {
+    /// Each axis from -1 to 1, `None` while the left stick is in its deadzone
+    pub movement: Option<(f64, f64)>,
+    /// Angle in degrees the right stick points, `None` while it's in its deadzone
+    pub aim: Option<f64>,
+}
+
+impl Binding {
+    /// Whether the name is one this build can read, anything else can never be pressed
+    fn is_known(&self) -> bool {
+        match self {
+            Binding::Key(name) => key_named(name).is_some(),
+            Binding::Mouse(name) => mouse_named(name).is_some(),
+            Binding::Gamepad(name) => gamepad_named(name).is_some(),
+        }
+    }
+
+    fn is_down(&self, input: &InputContext) -> bool {
+        match self {
+            Binding::Key(name) => key_named(name).map_or(false, |key| input::is_key_down(input, key)),
+            Binding::Mouse(name) => mouse_named(name).map_or(false, |button| input::is_mouse_button_down(input, button)),
+            Binding::Gamepad(name) => gamepad_named(name).map_or(false, |button| input::is_gamepad_button_down(input, GAMEPAD, button)),
+        }
+    }
+
+    fn is_pressed(&self, input: &InputContext) -> bool {
+        match self {
+            Binding::Key(name) => key_named(name).map_or(false, |key| input::is_key_pressed(input, key)),
+            Binding::Mouse(name) => mouse_named(name).map_or(false, |button| input::is_mouse_button_pressed(input, button)),
+            Binding::Gamepad(name) => gamepad_named(name).map_or(false, |button| input::is_gamepad_button_pressed(input, GAMEPAD, button)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(name) => name.clone(),
+            Binding::Mouse(name) => format!("Mouse {}", name),
+            Binding::Gamepad(name) => format!("Pad {}", name),
+        }
+    }
+}
+
+//
+// Action map
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActionMap {
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+    #[serde(default = "default_device")]
+    pub device: InputDevice,
+    /// How far either stick has to be pushed, from 0 to 1, before it counts
+    #[serde(default = "default_deadzone")]
+    pub deadzone: f64,
+}
+
+impl Default for ActionMap {
+    fn default() -> Self {
+        let key = |name: &str| Binding::Key(String::from(name));
+        let pad = |name: &str| Binding::Gamepad(String::from(name));
+        let mut bindings = BTreeMap::new();
+        bindings.insert(Action::MoveUp, vec![key("W"), key("Up"), pad("Up")]);
+        bindings.insert(Action::MoveDown, vec![key("S"), key("Down"), pad("Down")]);
+        bindings.insert(Action::MoveLeft, vec![key("A"), key("Left"), pad("Left")]);
+        bindings.insert(Action::MoveRight, vec![key("D"), key("Right"), pad("Right")]);
+        bindings.insert(Action::Fire, vec![Binding::Mouse(String::from("Left")), pad("RightTrigger")]);
+        bindings.insert(Action::Pause, vec![key("Escape"), pad("Start")]);
+        bindings.insert(Action::Confirm, vec![key("Enter"), key("Space"), pad("A")]);
+        ActionMap {
+            bindings,
+            device: default_device(),
+            deadzone: default_deadzone(),
+        }
+    }
+}
+
+impl ActionMap {
+    /// `<config dir>/asteroids/controls.ron`, falling back to the working directory
+    pub fn default_path() -> PathBuf {
+        dirs::config_dir()
+            .map(|dir| dir.join("asteroids"))
+            .unwrap_or_else(PathBuf::new)
+            .join("controls.ron")
+    }
+
+    /// Missing or corrupt files give the default bindings, as do any actions left out of the file
+    /// or left with nothing once unknown key and button names are dropped
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return ActionMap::default(),
+            Err(e) => {
+                eprintln!("Failed to read controls from {}: {}", path.display(), e);
+                return ActionMap::default();
+            }
+        };
+
+        match ron::de::from_str::<ActionMap>(&contents) {
+            Ok(mut map) => {
+                for (action, bindings) in map.bindings.iter_mut() {
+                    bindings.retain(|binding| {
+                        if !binding.is_known() {
+                            eprintln!("Ignoring unknown binding {:?} for {:?} in {}", binding, action, path.display());
+                        }
+                        binding.is_known()
+                    });
+                }
+
+                for (action, defaults) in ActionMap::default().bindings.into_iter() {
+                    let bindings = map.bindings.entry(action).or_insert_with(Vec::new);
+                    if bindings.is_empty() {
+                        *bindings = defaults;
+                    }
+                }
+                map
+            }
+            Err(e) => {
+                eprintln!("Ignoring corrupt controls file {}: {}", path.display(), e);
+                ActionMap::default()
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        fs::write(path, contents)
+    }
+
+    pub fn is_down(&self, input: &InputContext, action: Action) -> bool {
+        self.bindings.get(&action).map_or(false, |bindings| bindings.iter().any(|b| b.is_down(input)))
+    }
+
+    /// Only true on the frame one of the action's bindings went down
+    pub fn is_pressed(&self, input: &InputContext, action: Action) -> bool {
+        self.bindings.get(&action).map_or(false, |bindings| bindings.iter().any(|b| b.is_pressed(input)))
+    }
+
+    /// Makes `binding` the action's only binding
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.bindings.insert(action, vec![binding]);
+    }
+
+    /// Reads both sticks of the first gamepad, nothing if there isn't one
+    pub fn sticks(&self, input: &InputContext) -> Sticks {
+        if !input::is_gamepad_connected(input, GAMEPAD) {
+            return Sticks::default();
+        }
+
+        let axis = |axis| input::get_gamepad_axis_position(input, GAMEPAD, axis) as f64;
+        let movement = apply_deadzone(axis(GamepadAxis::LeftStickX), axis(GamepadAxis::LeftStickY), self.deadzone);
+        // Same convention as `Transform::get_angle_to`, 0 is straight up and y points down
+        let aim = apply_deadzone(axis(GamepadAxis::RightStickX), axis(GamepadAxis::RightStickY), self.deadzone)
+            .map(|(x, y)| x.atan2(-y).to_degrees());
+
+        Sticks { movement, aim }
+    }
+
+    /// e.g. "W / Up"
+    pub fn describe(&self, action: Action) -> String {
+        match self.bindings.get(&action) {
+            Some(bindings) if !bindings.is_empty() => bindings.iter().map(|b| b.name()).collect::<Vec<_>>().join(" / "),
+            _ => String::from("Unbound"),
+        }
+    }
+}
+
+/// Rescales a stick so the edge of the deadzone reads as 0, `None` inside it
+fn apply_deadzone(x: f64, y: f64, deadzone: f64) -> Option<(f64, f64)> {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= deadzone {
+        return None;
+    }
+
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0);
+    Some((x / magnitude * scaled, y / magnitude * scaled))
+}
+
+/// Whatever key or button went down this frame, for rebinding
+pub fn pressed_binding(input: &InputContext) -> Option<Binding> {
+    if let Some((name, _)) = KEYS.iter().find(|(_, key)| input::is_key_pressed(input, *key)) {
+        return Some(Binding::Key(String::from(*name)));
+    }
+    if let Some((name, _)) = GAMEPAD_BUTTONS.iter().find(|(_, button)| input::is_gamepad_button_pressed(input, GAMEPAD, *button)) {
+        return Some(Binding::Gamepad(String::from(*name)));
+    }
+    MOUSE_BUTTONS
+        .iter()
+        .find(|(_, button)| input::is_mouse_button_pressed(input, *button))
+        .map(|(name, _)| Binding::Mouse(String::from(*name)))
+}
+
+fn key_named(name: &str) -> Option<Key> {
+    KEYS.iter().find(|(n, _)| *n == name).map(|(_, key)| *key)
+}
+
+fn mouse_named(name: &str) -> Option<MouseButton> {
+    MOUSE_BUTTONS.iter().find(|(n, _)| *n == name).map(|(_, button)| *button)
+}
+
+fn gamepad_named(name: &str) -> Option<GamepadButton> {
+    GAMEPAD_BUTTONS.iter().find(|(n, _)| *n == name).map(|(_, button)| *button)
+}
+
+const GAMEPAD_BUTTONS: &[(&str, GamepadButton)] = &[
+    ("A", GamepadButton::A), ("B", GamepadButton::B), ("X", GamepadButton::X), ("Y", GamepadButton::Y),
+    ("Up", GamepadButton::Up), ("Down", GamepadButton::Down), ("Left", GamepadButton::Left), ("Right", GamepadButton::Right),
+    ("LeftShoulder", GamepadButton::LeftShoulder), ("RightShoulder", GamepadButton::RightShoulder),
+    ("LeftTrigger", GamepadButton::LeftTrigger), ("RightTrigger", GamepadButton::RightTrigger),
+    ("LeftStick", GamepadButton::LeftStick), ("RightStick", GamepadButton::RightStick),
+    ("Start", GamepadButton::Start), ("Back", GamepadButton::Back),
+];
+
+const MOUSE_BUTTONS: &[(&str, MouseButton)] = &[
+    ("Left", MouseButton::Left),
+    ("Right", MouseButton::Right),
+    ("Middle", MouseButton::Middle),
+];
+
+/// Keys that can be bound, by the name they're saved under
+const KEYS: &[(&str, Key)] = &[
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E), ("F", Key::F),
+    ("G", Key::G), ("H", Key::H), ("I", Key::I), ("J", Key::J), ("K", Key::K), ("L", Key::L),
+    ("M", Key::M), ("N", Key::N), ("O", Key::O), ("P", Key::P), ("Q", Key::Q), ("R", Key::R),
+    ("S", Key::S), ("T", Key::T), ("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X),
+    ("Y", Key::Y), ("Z", Key::Z),
+    ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4),
+    ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9),
+    ("Up", Key::Up), ("Down", Key::Down), ("Left", Key::Left), ("Right", Key::Right),
+    ("Space", Key::Space), ("Enter", Key::Enter), ("Escape", Key::Escape), ("Tab", Key::Tab),
+    ("Backspace", Key::Backspace),
+    ("LeftShift", Key::LeftShift), ("RightShift", Key::RightShift),
+    ("LeftCtrl", Key::LeftCtrl), ("RightCtrl", Key::RightCtrl),
+    ("LeftAlt", Key::LeftAlt), ("RightAlt", Key::RightAlt),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::with_file;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn deadzone_edge() {
+        assert_eq!(apply_deadzone(0.0, 0.0, 0.2), None);
+        assert_eq!(apply_deadzone(0.2, 0.0, 0.2), None);
+        assert_eq!(apply_deadzone(0.0, -0.2, 0.2), None);
+
+        let (x, y) = apply_deadzone(0.2 + 1e-6, 0.0, 0.2).unwrap();
+        assert!(x > 0.0 && x < 1e-5);
+        assert_eq!(y, 0.0);
+    }
+
+    #[test]
+    fn deadzone_rescales_to_full_range() {
+        let (x, y) = apply_deadzone(0.0, 1.0, 0.2).unwrap();
+        assert!(close(x, 0.0) && close(y, 1.0));
+
+        let (x, _) = apply_deadzone(0.6, 0.0, 0.2).unwrap();
+        assert!(close(x, 0.5));
+
+        // Corners of a square stick range are no longer than full tilt
+        let (x, y) = apply_deadzone(1.0, 1.0, 0.2).unwrap();
+        assert!(close((x * x + y * y).sqrt(), 1.0));
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Fire, Binding::Key(String::from("Space")));
+        map.device = InputDevice::Gamepad;
+        map.deadzone = 0.35;
+
+        let source = ron::ser::to_string_pretty(&map, ron::ser::PrettyConfig::default()).unwrap();
+        assert_eq!(ron::de::from_str::<ActionMap>(&source).unwrap(), map);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut map = ActionMap::default();
+        map.rebind(Action::Pause, Binding::Gamepad(String::from("Back")));
+
+        let loaded = with_file("controls-round-trip.ron", "", |path| {
+            map.save(path).unwrap();
+            ActionMap::load(path)
+        });
+        assert_eq!(loaded, map);
+    }
+
+    #[test]
+    fn unknown_names_fall_back_to_defaults() {
+        let source = r#"(
+            bindings: {
+                MoveUp: [Key("NotAKey")],
+                Fire: [Key("NotAKey"), Key("F")],
+            },
+        )"#;
+        let map = with_file("controls-unknown.ron", source, |path| ActionMap::load(path));
+
+        let defaults = ActionMap::default();
+        assert_eq!(map.bindings[&Action::MoveUp], defaults.bindings[&Action::MoveUp]);
+        assert_eq!(map.bindings[&Action::Fire], vec![Binding::Key(String::from("F"))]);
+        assert_eq!(map.bindings[&Action::Pause], defaults.bindings[&Action::Pause]);
+        assert_eq!(map.device, InputDevice::KeyboardMouse);
+    }
+
+    #[test]
+    fn corrupt_file_loads_defaults() {
+        let map = with_file("controls-corrupt.ron", "not ron (", |path| ActionMap::load(path));
+        assert_eq!(map, ActionMap::default());
+    }
+}
@@ -0,0 +1,98 @@
+use serde::Deserialize;
+
+use vermarine_lib::shipyard::EntityId;
+
+use crate::layers;
+
+//
+// Damage rules
+//
+// Which collisions hurt what. A rule applies when the victim's sensor on `victim` layer
+// overlaps something on `source` layer.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DamageRule {
+    pub victim: u64,
+    pub source: u64,
+    pub amount: i32,
+    /// Bullets are used up by hitting (subject to their pierce), anything else just keeps going
+    pub consumes_source: bool,
+    /// Whether a consumed source passes through the victim while it has iframes rather than being used up on it
+    pub passes_iframes: bool,
+}
+
+pub const DAMAGE_RULES: &[DamageRule] = &[
+    DamageRule { victim: layers::PLAYER, source: layers::ASTEROID | layers::ENEMY, amount: 1, consumes_source: false, passes_iframes: false },
+    // Enemy bullets fly through the player while they're recovering from a hit
+    DamageRule { victim: layers::PLAYER, source: layers::BULLET_ENEMY, amount: 1, consumes_source: true, passes_iframes: true },
+    DamageRule { victim: layers::ASTEROID, source: layers::BULLET_PLAYER, amount: 1, consumes_source: true, passes_iframes: false },
+    // Player bullets are still used up on an enemy that's flashing from a hit
+    DamageRule { victim: layers::ENEMY, source: layers::BULLET_PLAYER, amount: 1, consumes_source: true, passes_iframes: false },
+];
+
+pub fn find_rule(victim: u64, source: u64) -> Option<&'static DamageRule> {
+    DAMAGE_RULES.iter().find(|rule| rule.victim & victim > 0 && rule.source & source > 0)
+}
+
+//
+// What happens when something's health runs out, set per archetype with `death` in its `.ron`
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum OnDeath {
+    /// Just removed
+    Vanish,
+    /// Replaced by two copies `factor` times smaller, unless they'd be smaller than `min_radius`
+    Split { factor: f64, min_radius: f64 },
+    /// Leaves a burst that grows and fades out
+    Burst,
+}
+
+//
+// Queues filled and drained within a single frame
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Damage {
+    pub target: EntityId,
+    pub source: EntityId,
+    /// Where the source was when it hit, so splits can fly away from it
+    pub from: (f64, f64),
+    pub amount: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Death {
+    pub entity: EntityId,
+    pub from: (f64, f64),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DamageQueue {
+    pub damage: Vec<Damage>,
+    pub deaths: Vec<Death>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enemy_bullets_pass_through_a_flashing_player() {
+        let rule = find_rule(layers::PLAYER, layers::BULLET_ENEMY).unwrap();
+        assert!(rule.consumes_source);
+        assert!(rule.passes_iframes);
+    }
+
+    #[test]
+    fn player_bullets_are_used_up_on_a_flashing_enemy() {
+        let rule = find_rule(layers::ENEMY, layers::BULLET_PLAYER).unwrap();
+        assert!(rule.consumes_source);
+        assert!(!rule.passes_iframes);
+    }
+
+    #[test]
+    fn ramming_never_consumes() {
+        let rule = find_rule(layers::PLAYER, layers::ASTEROID).unwrap();
+        assert!(!rule.consumes_source);
+        assert_eq!(find_rule(layers::PLAYER, layers::ENEMY), Some(rule));
+    }
+}
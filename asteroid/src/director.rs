@@ -0,0 +1,312 @@
+use std::{
+    fs,
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    arena::Arena,
+    camera::CameraConfig,
+    timestep::TICK_RATE,
+};
+
+pub const DEFAULT_STAGE: &str = "assets/stages/default.ron";
+
+const TICKS_PER_MINUTE: f64 = TICK_RATE * 60.0;
+
+/// Shipped with the binary so a missing assets folder still gives a playable game
+const BUILTIN_STAGE: &str = include_str!("../../assets/stages/default.ron");
+
+//
+// Stage script, deserialized from `assets/stages/<name>.ron`
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Random,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Formation {
+    Single,
+    /// `size` archetypes side by side along the edge, `spacing` apart
+    Line { size: u32, spacing: f64 },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct SpawnGroup {
+    pub archetype: String,
+    /// Formations spawned over the wave
+    pub count: u32,
+    #[serde(default = "default_edge")]
+    pub edge: Edge,
+    #[serde(default = "default_formation")]
+    pub formation: Formation,
+    /// Frames after the wave starts before the first formation
+    #[serde(default)]
+    pub delay: u32,
+    /// Frames between formations
+    #[serde(default)]
+    pub interval: u32,
+}
+
+fn default_edge() -> Edge { Edge::Random }
+fn default_formation() -> Formation { Formation::Single }
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum Until {
+    /// Every group has spawned and no asteroids or enemies are left
+    Cleared,
+    /// This many frames after the wave started
+    Time(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Wave {
+    pub spawns: Vec<SpawnGroup>,
+    pub until: Until,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+pub struct Ramp {
+    /// Difficulty gained per minute survived
+    #[serde(default)]
+    pub per_minute: f64,
+    /// Difficulty gained every time the stage loops back to its first wave
+    #[serde(default)]
+    pub per_loop: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Stage {
+    pub name: String,
+    pub waves: Vec<Wave>,
+    #[serde(default)]
+    pub ramp: Ramp,
+    /// Playfield for this stage, `Arena::default()` if left out
+    #[serde(default)]
+    pub arena: Option<Arena>,
+    #[serde(default)]
+    pub camera: CameraConfig,
+}
+
+impl Stage {
+    /// Falls back to the built in stage if `path` is missing or invalid
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let stage = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|source| ron::de::from_str::<Stage>(&source).map_err(|e| e.to_string()));
+
+        match stage {
+            Ok(stage) if !stage.waves.is_empty() => stage,
+            Ok(_) => {
+                eprintln!("Stage {} has no waves, using the built in stage", path.display());
+                Stage::builtin()
+            }
+            Err(e) => {
+                eprintln!("Failed to load stage {}: {}", path.display(), e);
+                Stage::builtin()
+            }
+        }
+    }
+
+    pub fn builtin() -> Self {
+        ron::de::from_str(BUILTIN_STAGE).expect("built in stage is invalid")
+    }
+}
+
+//
+// Director unique
+//
+// Walks through the stage's waves, deciding what spawns each frame
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpawnOrder {
+    pub archetype: String,
+    pub edge: Edge,
+    pub formation: Formation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Director {
+    pub stage: Stage,
+    /// Index into `stage.waves`
+    pub wave: usize,
+    /// Times the stage has wrapped back to its first wave
+    pub loops: u32,
+    /// Frames since the current wave started
+    pub wave_timer: u32,
+    /// Frames since the run started
+    pub elapsed: u64,
+    /// Formations spawned so far by each group in the current wave
+    spawned: Vec<u32>,
+}
+
+impl Director {
+    pub fn new(stage: Stage) -> Self {
+        let spawned = vec![0; stage.waves[0].spawns.len()];
+        Director {
+            stage,
+            wave: 0,
+            loops: 0,
+            wave_timer: 0,
+            elapsed: 0,
+            spawned,
+        }
+    }
+
+    /// 1 at the start, growing with time survived and stage loops
+    pub fn difficulty(&self) -> f64 {
+        let minutes = self.elapsed as f64 / TICKS_PER_MINUTE;
+        1.0 + minutes * self.stage.ramp.per_minute + self.loops as f64 * self.stage.ramp.per_loop
+    }
+
+    /// Counting from 1 and carrying on across loops
+    pub fn wave_number(&self) -> usize {
+        self.loops as usize * self.stage.waves.len() + self.wave + 1
+    }
+
+    /// Advances a frame, returning what should spawn. `alive` is how many asteroids and enemies are left.
+    pub fn tick(&mut self, alive: usize) -> Vec<SpawnOrder> {
+        let difficulty = self.difficulty();
+        let wave = &self.stage.waves[self.wave];
+        let mut orders = vec![];
+        let mut finished_spawning = true;
+
+        for (group, spawned) in wave.spawns.iter().zip(self.spawned.iter_mut()) {
+            let count = (group.count as f64 * difficulty).ceil() as u32;
+            let interval = (group.interval as f64 / difficulty).round() as u32;
+
+            while *spawned < count && self.wave_timer >= group.delay + *spawned * interval {
+                *spawned += 1;
+                orders.push(SpawnOrder {
+                    archetype: group.archetype.clone(),
+                    edge: group.edge,
+                    formation: group.formation,
+                });
+            }
+
+            if *spawned < count {
+                finished_spawning = false;
+            }
+        }
+
+        let done = match wave.until {
+            Until::Cleared => finished_spawning && orders.is_empty() && alive == 0,
+            Until::Time(frames) => self.wave_timer >= frames,
+        };
+
+        self.wave_timer += 1;
+        self.elapsed += 1;
+
+        if done {
+            self.next_wave();
+        }
+
+        orders
+    }
+
+    fn next_wave(&mut self) {
+        self.wave += 1;
+        if self.wave >= self.stage.waves.len() {
+            self.wave = 0;
+            self.loops += 1;
+        }
+
+        self.wave_timer = 0;
+        self.spawned = vec![0; self.stage.waves[self.wave].spawns.len()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(count: u32, interval: u32) -> SpawnGroup {
+        SpawnGroup {
+            archetype: String::from("asteroid"),
+            count,
+            edge: Edge::Random,
+            formation: Formation::Single,
+            delay: 0,
+            interval,
+        }
+    }
+
+    /// A timed wave spawning two asteroids 2 frames apart, then a wave that waits to be cleared
+    fn stage(ramp: Ramp) -> Stage {
+        Stage {
+            name: String::from("test"),
+            waves: vec![
+                Wave { spawns: vec![group(2, 2)], until: Until::Time(3) },
+                Wave { spawns: vec![], until: Until::Cleared },
+            ],
+            ramp,
+            arena: None,
+            camera: CameraConfig::default(),
+        }
+    }
+
+    fn spawns_per_tick(director: &mut Director, ticks: usize) -> Vec<usize> {
+        (0..ticks).map(|_| director.tick(0).len()).collect()
+    }
+
+    #[test]
+    fn builtin_stage_parses() {
+        assert!(!Stage::builtin().waves.is_empty());
+    }
+
+    #[test]
+    fn timed_wave_spawns_on_interval_then_moves_on() {
+        let mut director = Director::new(stage(Ramp::default()));
+        assert_eq!(spawns_per_tick(&mut director, 3), vec![1, 0, 1]);
+        assert_eq!(director.wave, 0);
+
+        director.tick(0);
+        assert_eq!(director.wave, 1);
+        assert_eq!(director.wave_timer, 0);
+        assert_eq!(director.wave_number(), 2);
+    }
+
+    #[test]
+    fn cleared_wave_waits_for_everything_to_die() {
+        let mut director = Director::new(stage(Ramp::default()));
+        spawns_per_tick(&mut director, 4);
+        assert_eq!(director.wave, 1);
+
+        director.tick(3);
+        director.tick(1);
+        assert_eq!(director.wave, 1);
+
+        director.tick(0);
+        assert_eq!(director.wave, 0);
+        assert_eq!(director.loops, 1);
+        assert_eq!(director.wave_number(), 3);
+    }
+
+    #[test]
+    fn difficulty_ramps_per_minute() {
+        let mut director = Director::new(stage(Ramp { per_minute: 0.5, per_loop: 0.0 }));
+        assert_eq!(director.difficulty(), 1.0);
+
+        director.elapsed = TICKS_PER_MINUTE as u64 * 2;
+        assert_eq!(director.difficulty(), 2.0);
+    }
+
+    #[test]
+    fn loops_ramp_spawn_counts() {
+        let mut director = Director::new(stage(Ramp { per_minute: 0.0, per_loop: 1.0 }));
+        spawns_per_tick(&mut director, 5);
+        assert_eq!(director.loops, 1);
+        assert_eq!(director.difficulty(), 2.0);
+
+        // Twice the asteroids at half the interval
+        assert_eq!(spawns_per_tick(&mut director, 4), vec![1, 1, 1, 1]);
+        assert_eq!(director.wave, 1);
+    }
+}
@@ -0,0 +1,61 @@
+use vermarine_lib::shipyard::*;
+
+use crate::components::{
+    PowerUpKind,
+    Team,
+};
+
+//
+// Gameplay events
+//
+// Emitted by systems as things happen so scoring, particles, audio and stats can react
+// without borrowing whatever storages the emitting system uses.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameEvent {
+    /// Something was built from an archetype
+    Spawned { entity: EntityId, x: f64, y: f64 },
+    /// The player lost health, `hp` is what's left
+    PlayerHit { x: f64, y: f64, hp: i32 },
+    /// Anything with `Health` ran out of it, sent before the entity is deleted
+    EntityDied { entity: EntityId, x: f64, y: f64 },
+    AsteroidSplit { x: f64, y: f64, radius: f64 },
+    AsteroidDestroyed { x: f64, y: f64, radius: f64 },
+    EnemyDestroyed { x: f64, y: f64, score: u64 },
+    /// The player fired `count` projectiles
+    ShotFired { x: f64, y: f64, count: u32 },
+    /// A bullet hit something for the first time
+    BulletLanded { x: f64, y: f64, team: Team },
+    BulletBounced { x: f64, y: f64 },
+    BulletDestroyed { x: f64, y: f64 },
+    PowerUpCollected { kind: PowerUpKind, x: f64, y: f64 },
+}
+
+//
+// Events unique
+//
+// Holds a single frame of events. It's cleared by `clear_events` at the start of every frame,
+// so systems see whatever systems before them emitted this frame, and code outside the
+// workload sees the whole frame once `run_frame` returns.
+#[derive(Clone, Debug, Default)]
+pub struct Events {
+    events: Vec<GameEvent>,
+}
+
+impl Events {
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GameEvent> {
+        self.events.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+pub fn clear_events(mut events: UniqueViewMut<Events>) {
+    events.clear();
+}
@@ -0,0 +1,141 @@
+use vermarine_lib::{
+    shipyard::*,
+    tetra::graphics::Camera,
+};
+
+use crate::{
+    components::*,
+    input::InputFrame,
+    init_world,
+    player_is_dead,
+    run_frame,
+    AsteroidGame,
+    SpriteTextures,
+};
+
+/// Strafes in a slow square while sweeping fire around the player
+pub fn scripted_input(frame: u64) -> InputFrame {
+    let leg = (frame / 60) % 4;
+    InputFrame {
+        move_right: leg == 0,
+        move_down: leg == 1,
+        move_left: leg == 2,
+        move_up: leg == 3,
+        move_axis: None,
+        lmb_down: true,
+        shoot_angle: (frame * 7 % 360) as f64,
+    }
+}
+
+//
+// Results of a headless run
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    pub seed: u64,
+    pub frames: u64,
+    pub died_on_frame: Option<u64>,
+    pub hp: i32,
+    pub score: Score,
+    pub asteroids: usize,
+    pub enemies: usize,
+    pub bullets: usize,
+}
+
+/// Creates a world that can run the "Main" and "Physics" workloads without a tetra `Context`.
+/// No "Rendering" workload is added so sprites are created but never drawn.
+pub fn create_world(seed: u64) -> World {
+    let mut world = World::new();
+    world.add_unique(Camera::new(1280.0, 720.0));
+    // Texture handles are only ever read by the renderer so any value will do
+    world.add_unique(SpriteTextures { asteroid: 0, square: 1 });
+
+    init_world(&mut world, seed);
+    world
+}
+
+/// Steps `world` for up to `frames` frames, asking `script` for the input of each frame.
+/// Stops early if the player dies.
+pub fn simulate<F: FnMut(u64) -> InputFrame>(world: &World, frames: u64, mut script: F) -> SimulationReport {
+    let mut died_on_frame = None;
+    let mut frame = 0;
+
+    while frame < frames {
+        let input = script(frame);
+        world.run(|mut game: UniqueViewMut<AsteroidGame>| {
+            input.apply(&mut game);
+        });
+
+        run_frame(world);
+        frame += 1;
+
+        if player_is_dead(world) {
+            died_on_frame = Some(frame);
+            break;
+        }
+    }
+
+    world.run(|
+        game: UniqueView<AsteroidGame>,
+        score: UniqueView<Score>,
+        healths: View<Health>,
+        players: View<Player>,
+        asteroids: View<Asteroid>,
+        enemies: View<Enemy>,
+        bullets: View<Bullet>,| {
+            SimulationReport {
+                seed: game.seed,
+                frames: frame,
+                died_on_frame,
+                score: *score,
+                hp: (&healths, &players).iter().next().map(|(h, _)| h.hp).unwrap_or(0),
+                asteroids: asteroids.iter().count(),
+                enemies: enemies.iter().count(),
+                bullets: bullets.iter().count(),
+            }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use vermarine_lib::physics::{
+        PhysicsBody,
+        world::PhysicsWorld,
+    };
+
+    use super::*;
+
+    const FRAMES: u64 = 600;
+
+    fn run(seed: u64) -> (SimulationReport, Vec<(f64, f64)>) {
+        let world = create_world(seed);
+        let report = simulate(&world, FRAMES, scripted_input);
+        let positions = world.run(|
+            physics_bodies: View<PhysicsBody>,
+            asteroids: View<Asteroid>,
+            physics_world: UniqueView<PhysicsWorld>,| {
+                (&physics_bodies, &asteroids)
+                    .iter()
+                    .with_id()
+                    .map(|(id, _)| {
+                        let transform = physics_world.transform(id);
+                        (transform.x, transform.y)
+                    })
+                    .collect()
+        });
+        (report, positions)
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        assert_eq!(run(1234), run(1234));
+    }
+
+    #[test]
+    fn different_seed_different_run() {
+        // Leave the seed out so the runs themselves have to differ
+        let without_seed = |(report, positions): (SimulationReport, Vec<(f64, f64)>)| {
+            (SimulationReport { seed: 0, ..report }, positions)
+        };
+        assert_ne!(without_seed(run(1234)), without_seed(run(5678)));
+    }
+}
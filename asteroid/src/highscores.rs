@@ -0,0 +1,209 @@
+use std::{
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+pub const MAX_ENTRIES: usize = 10;
+pub const INITIALS_LEN: usize = 3;
+
+//
+// High scores
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighScoreEntry {
+    pub name: String,
+    pub score: u64,
+    pub seed: u64,
+    pub frames: u64,
+    /// Seconds since the unix epoch
+    pub date: u64,
+}
+
+impl HighScoreEntry {
+    pub fn new(name: String, score: u64, seed: u64, frames: u64) -> Self {
+        let date = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        HighScoreEntry { name, score, seed, frames, date }
+    }
+
+    /// `date` as YYYY-MM-DD (UTC)
+    pub fn date_string(&self) -> String {
+        let (y, m, d) = civil_from_days((self.date / 86400) as i64);
+        format!("{:04}-{:02}-{:02}", y, m, d)
+    }
+}
+
+/// Ranked best first, never longer than `MAX_ENTRIES`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HighScores {
+    pub entries: Vec<HighScoreEntry>,
+}
+
+impl HighScores {
+    /// `<data dir>/asteroids/highscores.ron`, falling back to the working directory
+    pub fn default_path() -> PathBuf {
+        dirs::data_dir()
+            .map(|dir| dir.join("asteroids"))
+            .unwrap_or_else(PathBuf::new)
+            .join("highscores.ron")
+    }
+
+    /// Missing or corrupt files give an empty table
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return HighScores::default(),
+            Err(e) => {
+                eprintln!("Failed to read high scores from {}: {}", path.display(), e);
+                return HighScores::default();
+            }
+        };
+
+        match ron::de::from_str::<HighScores>(&contents) {
+            Ok(mut scores) => {
+                scores.sort();
+                scores
+            }
+            Err(e) => {
+                eprintln!("Ignoring corrupt high score file {}: {}", path.display(), e);
+                HighScores::default()
+            }
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        fs::write(path, contents)
+    }
+
+    pub fn qualifies(&self, score: u64) -> bool {
+        score > 0 && (self.entries.len() < MAX_ENTRIES || self.entries.iter().any(|e| score > e.score))
+    }
+
+    /// Inserts `entry` at its rank, returning the rank if it made the table
+    pub fn insert(&mut self, entry: HighScoreEntry) -> Option<usize> {
+        let rank = self.entries.iter().position(|e| entry.score > e.score).unwrap_or(self.entries.len());
+        if rank >= MAX_ENTRIES {
+            return None;
+        }
+
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+
+    fn sort(&mut self) {
+        self.entries.sort_by(|a, b| b.score.cmp(&a.score));
+        self.entries.truncate(MAX_ENTRIES);
+    }
+}
+
+// Howard Hinnant's days-to-civil algorithm
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let y = yoe + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        temp_path,
+        with_file,
+    };
+
+    fn entry(score: u64) -> HighScoreEntry {
+        HighScoreEntry { name: String::from("AAA"), score, seed: 0, frames: 0, date: 0 }
+    }
+
+    fn full_table() -> HighScores {
+        let mut scores = HighScores::default();
+        for score in (1..=MAX_ENTRIES as u64).map(|i| i * 100) {
+            scores.insert(entry(score));
+        }
+        scores
+    }
+
+    #[test]
+    fn corrupt_file_loads_empty() {
+        let scores = with_file("highscores-corrupt.ron", "this is not ron (", |path| HighScores::load(path));
+        assert_eq!(scores, HighScores::default());
+    }
+
+    #[test]
+    fn missing_file_loads_empty() {
+        assert_eq!(HighScores::load(temp_path("highscores-missing.ron")), HighScores::default());
+    }
+
+    #[test]
+    fn insert_keeps_best_first() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.insert(entry(200)), Some(0));
+        assert_eq!(scores.insert(entry(500)), Some(0));
+        assert_eq!(scores.insert(entry(300)), Some(1));
+        assert_eq!(scores.insert(entry(100)), Some(3));
+
+        let order: Vec<u64> = scores.entries.iter().map(|e| e.score).collect();
+        assert_eq!(order, vec![500, 300, 200, 100]);
+    }
+
+    #[test]
+    fn table_never_grows_past_max() {
+        let mut scores = full_table();
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+
+        assert!(!scores.qualifies(100));
+        assert_eq!(scores.insert(entry(50)), None);
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+
+        assert!(scores.qualifies(150));
+        assert_eq!(scores.insert(entry(150)), Some(MAX_ENTRIES - 1));
+        assert_eq!(scores.entries.len(), MAX_ENTRIES);
+        assert_eq!(scores.entries.last().unwrap().score, 150);
+    }
+
+    #[test]
+    fn zero_never_qualifies() {
+        assert!(!HighScores::default().qualifies(0));
+        assert!(HighScores::default().qualifies(1));
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19723), (2024, 1, 1));
+    }
+}
@@ -0,0 +1,54 @@
+use vermarine_lib::shipyard::*;
+
+use crate::{
+    components::*,
+    director::Director,
+};
+
+//
+// What the HUD shows, read out of the world after a tick so drawing doesn't need any of its storages
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HudStats {
+    pub hp: i32,
+    pub max_hp: i32,
+    pub score: u64,
+    pub wave: usize,
+    /// From 0 right after firing to 1 when ready, see `Weapon::readiness`
+    pub weapon_ready: f32,
+    pub projectile_count: u32,
+    /// Timed power-ups on the player with the ticks they have left
+    pub effects: Vec<(PowerUpKind, i32)>,
+}
+
+impl HudStats {
+    pub fn from_world(world: &World) -> Self {
+        world.run(|
+            players: View<Player>,
+            healths: View<Health>,
+            weapons: View<Weapon>,
+            effects: View<TimedEffects>,
+            score: UniqueView<Score>,
+            director: UniqueView<Director>,| {
+                let mut stats = HudStats {
+                    score: score.total(),
+                    wave: director.wave_number(),
+                    ..HudStats::default()
+                };
+
+                if let Some((_, health)) = (&players, &healths).iter().next() {
+                    stats.hp = health.hp.max(0);
+                    stats.max_hp = health.max;
+                }
+                if let Some((_, weapon)) = (&players, &weapons).iter().next() {
+                    stats.weapon_ready = weapon.readiness();
+                    stats.projectile_count = weapon.projectile_count;
+                }
+                if let Some((_, effects)) = (&players, &effects).iter().next() {
+                    stats.effects = effects.effects.clone();
+                }
+
+                stats
+        })
+    }
+}
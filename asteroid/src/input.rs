@@ -0,0 +1,38 @@
+use crate::AsteroidGame;
+
+//
+// Input for a single frame, fed into `AsteroidGame` in place of live input
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct InputFrame {
+    pub move_left: bool,
+    pub move_right: bool,
+    pub move_up: bool,
+    pub move_down: bool,
+    pub move_axis: Option<(f64, f64)>,
+    pub lmb_down: bool,
+    pub shoot_angle: f64,
+}
+
+impl InputFrame {
+    pub fn from_game(game: &AsteroidGame) -> Self {
+        InputFrame {
+            move_left: game.move_left,
+            move_right: game.move_right,
+            move_up: game.move_up,
+            move_down: game.move_down,
+            move_axis: game.move_axis,
+            lmb_down: game.lmb_down,
+            shoot_angle: game.shoot_angle,
+        }
+    }
+
+    pub fn apply(&self, game: &mut AsteroidGame) {
+        game.move_left = self.move_left;
+        game.move_right = self.move_right;
+        game.move_up = self.move_up;
+        game.move_down = self.move_down;
+        game.move_axis = self.move_axis;
+        game.lmb_down = self.lmb_down;
+        game.shoot_angle = self.shoot_angle;
+    }
+}
@@ -0,0 +1,319 @@
+pub mod components;
+pub mod systems;
+pub mod consts;
+pub mod headless;
+pub mod input;
+pub mod replay;
+pub mod highscores;
+pub mod archetypes;
+pub mod patterns;
+pub mod director;
+pub mod arena;
+pub mod timestep;
+pub mod damage;
+pub mod events;
+pub mod particles;
+pub mod camera;
+pub mod hud;
+pub mod stats;
+pub mod controls;
+
+#[cfg(test)]
+mod testing;
+
+pub use consts::*;
+
+use vermarine_lib::{
+    shipyard::{
+        *,
+    },
+    tetra::{
+        graphics::{
+            Color,
+            Camera,
+        },
+        math::{
+            Vec2,
+        },
+    },
+    physics::{
+        PhysicsWorkloadCreator,
+        PhysicsWorkloadSystems,
+        PhysicsBody,
+        CollisionBody,
+        Collider,
+        world::{
+            PhysicsWorld,
+        }
+    },
+    components::{
+        Transform,
+    },
+    rendering::{
+        Drawables,
+        Sprite,
+        draw_buffer::{
+            DrawCommand,
+        },
+    },
+};
+
+use rand::rngs::StdRng;
+use rand::{
+    Rng,
+    SeedableRng,
+};
+
+use archetypes::{
+    Archetypes,
+    ARCHETYPE_DIR,
+};
+use arena::Arena;
+use components::*;
+use camera::CameraController;
+use damage::DamageQueue;
+use events::{
+    clear_events,
+    Events,
+};
+use particles::{
+    emit_particles,
+    move_particles,
+    ParticleRng,
+};
+use director::{
+    Director,
+    Stage,
+    DEFAULT_STAGE,
+};
+use systems::*;
+use timestep::PreviousTransforms;
+
+pub struct AsteroidGame {
+    pub seed: u64,
+    pub move_left: bool,
+    pub move_right: bool,
+    pub move_up: bool,
+    pub move_down: bool,
+    /// Analog movement from a stick, each axis from -1 to 1. Used in place of the `move_*` flags when set.
+    pub move_axis: Option<(f64, f64)>,
+    pub lmb_down: bool,
+    pub shoot_angle: f64,
+}
+
+impl AsteroidGame {
+    pub fn new(seed: u64) -> Self {
+        AsteroidGame {
+            seed,
+            move_left: false,
+            move_right: false,
+            move_down: false,
+            move_up: false,
+            move_axis: None,
+            lmb_down: false,
+            shoot_angle: 0f64,
+        }
+    }
+}
+
+//
+// Texture handles used by the simulation, resolved once so the "Main" workload
+// doesn't need the `Drawables` unique (and therefore a tetra `Context`)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpriteTextures {
+    pub asteroid: u64,
+    pub square: u64,
+}
+
+impl SpriteTextures {
+    pub fn from_drawables(drawables: &Drawables) -> Self {
+        SpriteTextures {
+            asteroid: drawables.alias[textures::ASTEROID],
+            square: drawables.alias[textures::SQUARE],
+        }
+    }
+}
+
+/// Picks a fresh seed for runs that weren't given one
+pub fn random_seed() -> u64 {
+    StdRng::from_entropy().gen()
+}
+
+/// Adds everything the "Main" and "Physics" workloads need to `world`.
+/// The caller is expected to have already added a `Camera` and `SpriteTextures` unique.
+/// Every random draw in the simulation goes through the `StdRng` unique seeded here,
+/// so the same seed and input always produce the same run.
+pub fn init_world(world: &mut World, seed: u64) {
+    world.add_unique(AsteroidGame::new(seed));
+    world.add_unique(StdRng::seed_from_u64(seed));
+    world.add_unique(Score::default());
+    world.add_unique(DamageQueue::default());
+    world.add_unique(Events::default());
+    world.add_unique(ParticleRng::new(seed));
+    world.add_unique(PreviousTransforms::default());
+    world.add_unique(Archetypes::load(ARCHETYPE_DIR));
+
+    let stage = Stage::load(DEFAULT_STAGE);
+    let arena = stage.arena.unwrap_or_default();
+    world.add_unique(arena);
+    world.add_unique(CameraController::new(stage.camera));
+    world.add_unique(Director::new(stage));
+
+    world.run(|controller: UniqueView<CameraController>, mut camera: UniqueViewMut<Camera>| {
+        camera.zoom = controller.config.zoom;
+    });
+
+    world
+        .add_workload("Main")
+        .with_system(system!(clear_events))
+        .with_system(system!(player_input))
+        .with_system(system!(iframe_counter))
+        .with_system(system!(survival_timer))
+        .with_system(system!(run_director))
+        .with_system(system!(run_emitters))
+        .with_system(system!(apply_physics))
+        .with_system(system!(move_bullets))
+        .with_system(system!(wrap_asteroids))
+        .with_system(system!(destroy_offscreen))
+        .with_system(system!(collect_damage))
+        .with_system(system!(apply_damage))
+        .with_system(system!(handle_deaths))
+        .with_system(system!(score_events))
+        .with_system(system!(restore_tints))
+        .with_system(system!(collect_powerups))
+        .with_system(system!(tick_effects))
+        .with_system(system!(update_fades))
+        .with_system(system!(destroy_bullets))
+        .with_system(system!(emit_particles))
+        .with_system(system!(move_particles))
+        .with_system(system!(move_camera))
+        .build();
+
+    world
+        .add_physics_workload(50.0, 50.0)
+        .with_physics_systems()
+        .build();
+
+    let player = world.run(
+        |textures: UniqueView<SpriteTextures>,
+        mut entities: EntitiesViewMut,
+        mut sprites: ViewMut<Sprite>,
+        mut healths: ViewMut<Health>,
+        mut physicses: ViewMut<Physics>,
+        mut players: ViewMut<Player>,
+        mut movements: ViewMut<PlayerMovement>,
+        mut weapons: ViewMut<Weapon>,
+        mut physics_bodies: ViewMut<PhysicsBody>,
+        mut physics_world: UniqueViewMut<PhysicsWorld>,
+        mut transforms: ViewMut<Transform>, | {
+            // Player
+            let player = entities.add_entity(
+                (
+                    &mut sprites,
+                    &mut healths,
+                    &mut physicses,
+                    &mut players,
+                    &mut movements,
+                    &mut weapons,
+                ),
+                (
+                    create_sprite(textures.square, 10.0, Color::rgb(0.0, 1.0, 0.0), draw_layers::PLAYER),
+                    Health::new(3, 20, Some(Color::RED)),
+                    Physics::default(),
+                    Player {},
+                    PlayerMovement::default(),
+                    Weapon::blaster(),
+                ),
+            );
+
+            physics_world.create_body(
+                &mut entities,
+                &mut physics_bodies,
+                player,
+                &mut transforms,
+                Transform::new(0.0, 0.0),
+                CollisionBody::from_parts(
+                    // Collider
+                    vec![Collider::half_extents(
+                        10f64,
+                        10f64,
+                        layers::PLAYER,
+                        layers::WALL,
+                    )],
+                    // Sensor
+                    vec![Collider::half_extents(
+                        10f64,
+                        10f64,
+                        layers::PLAYER,
+                        layers::ENEMY | layers::BULLET_ENEMY | layers::ASTEROID | layers::POWERUP,
+                    )]),
+            );
+
+            player
+        },
+    );
+
+    world.run(|entities: EntitiesViewMut, mut effects: ViewMut<TimedEffects>| {
+        entities.add_component(&mut effects, TimedEffects::default(), player);
+    });
+
+    for wall in arena.walls().iter() {
+        world.run_with_data(
+            create_wall,
+            *wall,
+        );
+    }
+}
+
+/// Steps the simulation by a single tick using whatever input is currently stored in `AsteroidGame`
+pub fn run_frame(world: &World) {
+    world.run_workload("Main");
+    world.run_workload("Physics");
+}
+
+pub fn current_score(world: &World) -> Score {
+    *world.borrow::<UniqueView<Score>>()
+}
+
+pub fn player_is_dead(world: &World) -> bool {
+    let players = world.borrow::<View<Player>>();
+    match players.iter().next() {
+        Some(_) => return false,
+        _ => return true,
+    };
+}
+
+fn create_wall(
+    data: (f64, f64, f64, f64),
+    textures: UniqueView<SpriteTextures>,
+    mut entities: EntitiesViewMut,
+    mut sprites: ViewMut<Sprite>,
+    mut physicses: ViewMut<Physics>,
+    mut physics_world: UniqueViewMut<PhysicsWorld>,
+    mut physics_bodies: ViewMut<PhysicsBody>,
+    mut transforms: ViewMut<Transform>, )
+    {
+        let (pos_x, pos_y, scale_x, scale_y) = data;
+
+        // Stationary square
+        let scale_calc = |s: f64| { (s / 1024.0 * 2.0) as f32 };
+        let square = entities.add_entity((&mut sprites, &mut physicses), (
+            Sprite::from_command(
+                DrawCommand::new(textures.square)
+                .scale(Vec2::new(scale_calc(scale_x), scale_calc(scale_y)))
+                .origin(Vec2::new(512.0, 512.0))
+                .color(Color::BLACK)
+                .draw_layer(draw_layers::WALL)
+            ),
+            Physics::default(),
+        ));
+
+        physics_world.create_body(
+            &mut entities,
+            &mut physics_bodies,
+            square,
+            &mut transforms,
+            Transform::new(pos_x, pos_y),
+            CollisionBody::from_collider(Collider::half_extents(scale_x, scale_y, layers::WALL, 0))
+        );
+}
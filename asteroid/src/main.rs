@@ -1,9 +1,3 @@
-mod components;
-mod systems;
-pub mod consts;
-
-pub use consts::*;
-
 use vermarine_lib::{
     shipyard::{
         *,
@@ -14,40 +8,37 @@ use vermarine_lib::{
             self, 
             Color,
             Camera,
+            DrawParams,
+            Texture,
+            text::{
+                Font,
+                Text,
+            },
         },
         input::{
             self,
             Key,
-            MouseButton,
             InputContext,
         },
         math::{
             Vec2,
         },
+        window,
         Context,
         ContextBuilder,
         Result,
     },
     physics::{
-        PhysicsWorkloadCreator,
-        PhysicsWorkloadSystems,
         PhysicsBody,
-        CollisionBody,
-        Collider,
         world::{
             PhysicsWorld,
         }
     },
-    components::{
-        Transform,
-    },
     rendering::{
         Drawables,
-        Sprite,
         RenderingWorkloadCreator,
         RenderingWorkloadSystems,
         draw_buffer::{
-            DrawCommand,
             DrawBuffer,
         },
     },
@@ -58,195 +49,426 @@ use vermarine_lib::{
     },
 };
 
-use rand::rngs::StdRng;
-use rand::SeedableRng;
+use std::{
+    env,
+    fs,
+    path::PathBuf,
+    rc::Rc,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
 
-use components::*;
-use systems::*;
+use asteroid::{
+    components::*,
+    input::InputFrame,
+    replay::Replay,
+    timestep::{
+        interpolate_transforms,
+        restore_transforms,
+        snapshot_transforms,
+        FixedTimestep,
+        TICK_RATE,
+    },
+    highscores::{
+        self,
+        HighScoreEntry,
+        HighScores,
+    },
+    controls::{
+        pressed_binding,
+        Action,
+        ActionMap,
+        InputDevice,
+    },
+    hud::HudStats,
+    stats::{
+        self,
+        RunStats,
+    },
+    init_world,
+    random_seed,
+    player_is_dead,
+    run_frame,
+    AsteroidGame,
+    SpriteTextures,
+};
 
+const REPLAY_DIR: &str = "replays";
+/// Ticks between writing the recording out mid-run, so a crash still leaves most of the run behind
+const REPLAY_SAVE_INTERVAL: usize = 300;
 
-pub struct AsteroidGame {
-    asteroid_timer: i32,
-    spinner_timer: i32,
-    move_left: bool,
-    move_right: bool,
-    move_up: bool,
-    move_down: bool,
-    lmb_down: bool,
-    shoot_angle: f64,
+/// Shared between every state on the stack
+struct Resources {
+    drawables: Drawables,
+    /// Seed every run uses when one was given on the command line
+    fixed_seed: Option<u64>,
+    high_scores: HighScores,
+    high_scores_path: PathBuf,
+    fullscreen: bool,
+    controls: ActionMap,
+    controls_path: PathBuf,
+    /// Left by `PauseState` for the `GameState` under it
+    pause_choice: Option<PauseChoice>,
 }
 
-impl AsteroidGame {
-    pub fn new(asteroid_timer: i32, spinner_timer: i32) -> Self {
-        AsteroidGame {
-            asteroid_timer,
-            spinner_timer,
-            move_left: false,
-            move_right: false,
-            move_down: false,
-            move_up: false,
-            lmb_down: false,
-            shoot_angle: 0f64,
-        }
+impl Resources {
+    fn new(ctx: &mut Context, fixed_seed: Option<u64>) -> Result<Resources> {
+        let high_scores_path = HighScores::default_path();
+        let controls_path = ActionMap::default_path();
+
+        Ok(Resources {
+            drawables: Drawables::new(ctx)?,
+            fixed_seed,
+            high_scores: HighScores::load(&high_scores_path),
+            high_scores_path,
+            fullscreen: false,
+            controls: ActionMap::load(&controls_path),
+            controls_path,
+            pause_choice: None,
+        })
     }
 }
 
-type Res = Drawables;
+type Res = Resources;
 fn main() -> tetra::Result {
+    let args = parse_args();
+    let fixed_seed = args.seed;
+
+    let replay = args.replay.and_then(|path| match Replay::load(&path) {
+        Ok(replay) => Some(replay),
+        Err(e) => {
+            eprintln!("Failed to load replay {}: {}", path.display(), e);
+            None
+        }
+    });
+
     ContextBuilder::new("Asteroids", 1280, 720)
         .show_mouse(true)
         .build()?
-        .run(|ctx| PushdownAutomaton::new(ctx, GameState::new, Drawables::new))
+        .run(|ctx| PushdownAutomaton::new(ctx, |_, _| Ok(TitleState::new(replay.clone())), |ctx| Resources::new(ctx, fixed_seed)))
+}
+
+struct Args {
+    seed: Option<u64>,
+    replay: Option<PathBuf>,
+}
+
+/// Reads `--seed N` and `--replay PATH` from the command line. Every run uses the seed when it's given,
+/// otherwise each run picks a fresh one.
+fn parse_args() -> Args {
+    let mut parsed = Args { seed: None, replay: None };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => {
+                match args.next().and_then(|n| n.parse().ok()) {
+                    Some(seed) => parsed.seed = Some(seed),
+                    None => usage_error("--seed expects a number"),
+                }
+            }
+            "--replay" => {
+                match args.next() {
+                    Some(path) => parsed.replay = Some(PathBuf::from(path)),
+                    None => usage_error("--replay expects a path"),
+                }
+            }
+            _ => usage_error(&format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    parsed
+}
+
+fn usage_error(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("Usage: asteroid [--seed N] [--replay PATH]");
+    std::process::exit(1);
+}
+
+fn create_world(ctx: &mut Context, res: &mut Res, seed: u64) -> World {
+    let mut world = World::new();
+    world.add_unique(Camera::with_window_size(ctx));
+    world.add_unique((*ctx.input_context()).clone());
+    world.add_unique(SpriteTextures::from_drawables(&res.drawables));
+    world.add_unique_non_send_sync(res.drawables.clone());
+
+    init_world(&mut world, seed);
+
+    world
+        .add_rendering_workload(ctx)
+        .with_rendering_systems()
+        .build();
+
+    world
+}
+
+/// Draws `world` `alpha` of the way between its last two ticks
+fn draw_world(world: &World, ctx: &mut Context, alpha: f32) {
+    // Cornflower blue, as is tradition
+    graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+
+    let current = interpolate_transforms(world, alpha);
+
+    world.run_workload("Rendering");
+    world.run(|mut draw_buff: UniqueViewMut<DrawBuffer>, mut camera: UniqueViewMut<Camera>| {
+        camera.update();
+        draw_buff.transform_mat = camera.as_matrix();
+    });
+    world.run_with_data(DrawBuffer::flush, ctx);
+
+    restore_transforms(world, current);
+}
+
+/// A vertical list of options picked with Up/Down and Enter, drawn under a heading
+struct Menu {
+    options: Vec<String>,
+    selected: usize,
+    heading: Text,
+    text: Text,
+}
+
+impl Menu {
+    fn new(heading: &str, options: Vec<String>) -> Self {
+        let mut menu = Menu {
+            options,
+            selected: 0,
+            heading: Text::new(heading, Font::default(), 48.0),
+            text: Text::new("", Font::default(), 28.0),
+        };
+        menu.refresh();
+        menu
+    }
+
+    fn set_options(&mut self, options: Vec<String>) {
+        self.options = options;
+        self.selected = self.selected.min(self.options.len().saturating_sub(1));
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        let mut content = String::new();
+        for (i, option) in self.options.iter().enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            content.push_str(&format!("{} {}\n", marker, option));
+        }
+        self.text.set_content(content);
+    }
+
+    /// The option picked this frame, if any
+    fn update(&mut self, ctx: &Context, controls: &ActionMap) -> Option<usize> {
+        if self.options.is_empty() {
+            return None;
+        }
+
+        let input = ctx.input_context();
+        if controls.is_pressed(input, Action::MoveUp) {
+            self.selected = (self.selected + self.options.len() - 1) % self.options.len();
+            self.refresh();
+        } else if controls.is_pressed(input, Action::MoveDown) {
+            self.selected = (self.selected + 1) % self.options.len();
+            self.refresh();
+        } else if controls.is_pressed(input, Action::Confirm) {
+            return Some(self.selected);
+        }
+
+        None
+    }
+
+    fn draw(&self, ctx: &mut Context, position: Vec2<f32>) {
+        graphics::draw(ctx, &self.heading, position);
+        graphics::draw(ctx, &self.text, position + Vec2::new(0.0, 80.0));
+    }
+}
+
+fn backed_out(ctx: &Context, controls: &ActionMap) -> bool {
+    controls.is_pressed(ctx.input_context(), Action::Pause)
+}
+
+/// The ranked high score table, one line per entry
+fn high_score_table(res: &Res) -> String {
+    let mut content = String::from("High Scores\n");
+    for (i, entry) in res.high_scores.entries.iter().enumerate() {
+        content.push_str(&format!(
+            "{:>2}. {:<3} {:>8}  {:>6}  seed {}  {}\n",
+            i + 1,
+            entry.name,
+            entry.score,
+            stats::time_string(entry.frames),
+            entry.seed,
+            entry.date_string(),
+        ));
+    }
+    content
+}
+
+/// Screen space overlay drawn on top of the world
+struct Hud {
+    stats: Option<HudStats>,
+    pips: Text,
+    info: Text,
+}
+
+impl Hud {
+    fn new() -> Self {
+        Hud {
+            stats: None,
+            pips: Text::new("", Font::default(), 32.0),
+            info: Text::new("", Font::default(), 24.0),
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, world: &World) {
+        let stats = HudStats::from_world(world);
+
+        // Only lay the text out again when something on it changed
+        if self.stats.as_ref() != Some(&stats) {
+            let mut pips = String::new();
+            for i in 0..stats.max_hp {
+                pips.push_str(if i < stats.hp { "[+]" } else { "[ ]" });
+            }
+            self.pips.set_content(pips);
+
+            let ready = (stats.weapon_ready * 10.0).round() as usize;
+            let mut info = format!(
+                "Score {}\nWave {}\nFire [{}{}] x{}",
+                stats.score,
+                stats.wave,
+                "#".repeat(ready),
+                "-".repeat(10 - ready.min(10)),
+                stats.projectile_count,
+            );
+            for (kind, ticks) in stats.effects.iter() {
+                info.push_str(&format!("\n{:?} {}s", kind, (*ticks as f64 / TICK_RATE).ceil()));
+            }
+            self.info.set_content(info);
+
+            self.stats = Some(stats);
+        }
+
+        // Undo the camera so this stays put on screen
+        graphics::reset_transform_matrix(ctx);
+        graphics::draw(ctx, &self.pips, DrawParams::new().position(Vec2::new(16.0, 12.0)).color(Color::RED));
+        graphics::draw(ctx, &self.info, Vec2::new(16.0, 52.0));
+    }
 }
 
 struct GameState {
-    world: World,
+    /// Shared with `PauseState` so it can keep drawing the game underneath
+    world: Rc<World>,
+    recording: Replay,
+    /// Picked when the run starts so every save overwrites the same file
+    recording_path: PathBuf,
+    /// Frames in the file last written, so nothing is saved twice
+    saved_frames: usize,
+    stats: RunStats,
+    timestep: FixedTimestep,
+    hud: Hud,
 }
 
 impl PDAState<Res> for GameState {
-    fn update(&mut self, ctx: &mut Context, _: &mut Res) -> Result<Trans<Res>> {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        match res.pause_choice.take() {
+            Some(PauseChoice::Restart) => {
+                self.save_recording();
+                let seed = self.recording.seed;
+                return Ok(Trans::Switch(Box::new(GameState::with_seed(ctx, res, seed)?)));
+            }
+            Some(PauseChoice::Quit) => {
+                self.save_recording();
+                return Ok(Trans::Pop);
+            }
+            None => { }
+        }
+
+        if res.controls.is_pressed(ctx.input_context(), Action::Pause) {
+            // Otherwise the time spent paused would all be caught up on when resuming
+            self.timestep.reset();
+            return Ok(Trans::Push(Box::new(PauseState::new(ctx, self.world.clone())?)));
+        }
+
         let input_ctx = ctx.input_context();
         self.world.run(|mut ctx: UniqueViewMut<InputContext>| {
             *ctx = (*input_ctx).clone();
         });
 
-        self.handle_input();
-        self.world.run_workload("Main");
-        self.world.run_workload("Physics");
+        for _ in 0..self.timestep.advance() {
+            snapshot_transforms(&self.world);
+
+            self.handle_input(&res.controls);
+            let recording = &mut self.recording;
+            self.world.run(|game: UniqueView<AsteroidGame>| {
+                recording.record(InputFrame::from_game(&game));
+            });
+            if self.recording.frames.len() % REPLAY_SAVE_INTERVAL == 0 {
+                self.save_recording();
+            }
+            run_frame(&self.world);
+            self.stats.record(&self.world);
 
-        if self.player_is_dead() {
-            return Ok(Trans::Switch(Box::new(DeadState)));
+            if player_is_dead(&self.world) {
+                self.save_recording();
+                return Ok(Trans::Switch(Box::new(DeadState::new(res, self.stats, true))));
+            }
         }
         Ok(Trans::None)
     }
 
     fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
-        // Cornflower blue, as is tradition
-        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
-
-        self.world.run_workload("Rendering");
-        self.world.run(|mut draw_buff: UniqueViewMut<DrawBuffer>, mut camera: UniqueViewMut<Camera>| {
-            camera.update();
-            draw_buff.transform_mat = camera.as_matrix();
-        });
-        self.world.run_with_data(DrawBuffer::flush, ctx);
+        draw_world(&self.world, ctx, self.timestep.alpha());
+        self.hud.draw(ctx, &self.world);
 
         Ok(())
     }
 }
 
+/// Catches every way out of a run that doesn't go through `save_recording` itself,
+/// including the window closing and panics unwinding the state stack
+impl Drop for GameState {
+    fn drop(&mut self) {
+        self.save_recording();
+    }
+}
+
 impl GameState {
+    /// Uses the seed from the command line if there was one
     fn new(ctx: &mut Context, res: &mut Res) -> Result<GameState> {
-        let mut world = World::new();
-        world.add_unique(AsteroidGame::new(50i32, 50i32));
-        world.add_unique(StdRng::from_entropy());
-        world.add_unique(Camera::with_window_size(ctx));
-        world.add_unique((*ctx.input_context()).clone());
-        world.add_unique_non_send_sync((*res).clone());
-
-        world.run(|mut camera: UniqueViewMut<Camera>| {
-            camera.zoom = 1.0;
-        });
+        let seed = res.fixed_seed.unwrap_or_else(random_seed);
+        GameState::with_seed(ctx, res, seed)
+    }
 
-        world
-            .add_workload("Main")
-            .with_system(system!(player_input))
-            .with_system(system!(iframe_counter))
-            .with_system(system!(spawn_asteroids))
-            .with_system(system!(spawn_spinners))
-            .with_system(system!(shoot_spinners))
-            .with_system(system!(apply_physics))
-            .with_system(system!(move_player_bullets))
-            .with_system(system!(wrap_asteroids))
-            .with_system(system!(destroy_offscreen))
-            .with_system(system!(player_damage))
-            .with_system(system!(asteroid_damage))
-            .with_system(system!(destroy_bullets))
-            .with_system(system!(move_camera))
-            .build();
-        
-        world
-            .add_physics_workload(50.0, 50.0)
-            .with_physics_systems()
-            .build();
-
-        world
-            .add_rendering_workload(ctx)
-            .with_rendering_systems()
-            .build();
-
-        world.run(
-            |drawables: NonSendSync<UniqueView<Drawables>>,
-            mut entities: EntitiesViewMut,
-            mut sprites: ViewMut<Sprite>,
-            mut healths: ViewMut<Health>,
-            mut physicses: ViewMut<Physics>,
-            mut players: ViewMut<Player>,
-            mut physics_bodies: ViewMut<PhysicsBody>,
-            mut physics_world: UniqueViewMut<PhysicsWorld>,
-            mut transforms: ViewMut<Transform>, | {
-                // Player
-                let player = entities.add_entity(
-                    (
-                        &mut sprites,
-                        &mut healths,
-                        &mut physicses,
-                        &mut players,
-                    ),
-                    (
-                        create_sprite(drawables.alias[textures::SQUARE], 10.0, Color::rgb(0.0, 1.0, 0.0), draw_layers::PLAYER),
-                        Health::new(3, 20, Some(Color::RED)),
-                        Physics::default(),
-                        Player {},
-                    ),
-                );
-
-                physics_world.create_body(
-                    &mut entities,
-                    &mut physics_bodies,
-                    player,
-                    &mut transforms,
-                    Transform::new(0.0, 0.0),
-                    CollisionBody::from_parts(
-                        // Collider
-                        vec![Collider::half_extents(
-                            10f64, 
-                            10f64, 
-                            layers::PLAYER, 
-                            layers::WALL,
-                        )], 
-                        // Sensor
-                        vec![Collider::half_extents(
-                            10f64,
-                            10f64,
-                            layers::PLAYER,
-                            layers::ENEMY | layers::BULLET_ENEMY | layers::ASTEROID,
-                        )]),
-                );
-            },
-        );
+    fn with_seed(ctx: &mut Context, res: &mut Res, seed: u64) -> Result<GameState> {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
 
-        world.run_with_data(
-            create_wall,
-            (-1020.0, 0.0, 20.0, 540.0),
-        );
-        world.run_with_data(
-            create_wall,
-            (1020.0, 0.0, 20.0, 540.0),
-        );
-        world.run_with_data(
-            create_wall,
-            (0.0, -520.0, 1040.0, 20.0),
-        );
-        world.run_with_data(
-            create_wall,
-            (0.0, 520.0, 1040.0, 20.0),
-        );
+        Ok(GameState {
+            world: Rc::new(create_world(ctx, res, seed)),
+            recording: Replay::new(seed),
+            recording_path: PathBuf::from(REPLAY_DIR).join(format!("{}-{}.replay", seed, time)),
+            saved_frames: 0,
+            stats: RunStats::new(seed),
+            timestep: FixedTimestep::new(TICK_RATE),
+            hud: Hud::new(),
+        })
+    }
 
-        Ok(GameState { world })
+    /// Writes the run's input so far to `replays/<seed>-<unix time>.replay`
+    fn save_recording(&mut self) {
+        if self.recording.frames.len() == self.saved_frames {
+            return;
+        }
+
+        match self.recording.save(&self.recording_path) {
+            Ok(()) => self.saved_frames = self.recording.frames.len(),
+            Err(e) => eprintln!("Failed to save replay to {}: {}", self.recording_path.display(), e),
+        }
     }
 
-    fn handle_input(&mut self) {
+    fn handle_input(&mut self, controls: &ActionMap) {
         self.world.run(|
             ctx: UniqueView<InputContext>,
             mut game: UniqueViewMut<AsteroidGame>, 
@@ -254,12 +476,22 @@ impl GameState {
             players: View<Player>, 
             physics_world: UniqueView<PhysicsWorld>,
             camera: UniqueView<Camera>, | {
-                game.move_right = input::is_key_down(&ctx, Key::D);
-                game.move_left = input::is_key_down(&ctx, Key::A);
-                game.move_up = input::is_key_down(&ctx, Key::W);
-                game.move_down = input::is_key_down(&ctx, Key::S);
-                game.lmb_down = input::is_mouse_button_down(&ctx, MouseButton::Left);
-                if game.lmb_down {
+                game.move_right = controls.is_down(&ctx, Action::MoveRight);
+                game.move_left = controls.is_down(&ctx, Action::MoveLeft);
+                game.move_up = controls.is_down(&ctx, Action::MoveUp);
+                game.move_down = controls.is_down(&ctx, Action::MoveDown);
+                game.lmb_down = controls.is_down(&ctx, Action::Fire);
+                game.move_axis = None;
+
+                if controls.device == InputDevice::Gamepad {
+                    let sticks = controls.sticks(&ctx);
+                    game.move_axis = sticks.movement;
+                    // Pushing the right stick both aims and fires, the fire binding keeps the last aim
+                    if let Some(angle) = sticks.aim {
+                        game.lmb_down = true;
+                        game.shoot_angle = angle;
+                    }
+                } else if game.lmb_down {
                     let body = match (&physics_bodies, &players).iter().with_id().next() {
                         Some((id, _)) => id,
                         _ => return,
@@ -274,65 +506,654 @@ impl GameState {
                 }
             });
     }
+}
 
-    fn player_is_dead(&self) -> bool {
-        let players = self.world.borrow::<View<Player>>();
-        match players.iter().next() {
-            Some(_) => return false,
-            _ => return true,
-        };
+/// What was picked in `PauseState`, acted on by `GameState` or `ReplayState` once it's back on top
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PauseChoice {
+    Restart,
+    Quit,
+}
+
+/// Pushed over `GameState` or `ReplayState`, which don't update underneath it so nothing in the world moves on
+struct PauseState {
+    world: Rc<World>,
+    menu: Menu,
+    /// Single pixel stretched over the screen to dim the game
+    dim: Texture,
+}
+
+impl PauseState {
+    fn new(ctx: &mut Context, world: Rc<World>) -> Result<PauseState> {
+        Ok(PauseState {
+            world,
+            menu: Menu::new("Paused", vec![
+                String::from("Resume"),
+                String::from("Restart"),
+                String::from("Quit to title"),
+            ]),
+            dim: Texture::from_rgba(ctx, 1, 1, &[255, 255, 255, 255])?,
+        })
     }
 }
 
-struct DeadState;
+impl PDAState<Res> for PauseState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
 
-impl PDAState<Res> for DeadState {
+        Ok(match self.menu.update(ctx, &res.controls) {
+            Some(0) => Trans::Pop,
+            Some(1) => {
+                res.pause_choice = Some(PauseChoice::Restart);
+                Trans::Pop
+            }
+            Some(_) => {
+                res.pause_choice = Some(PauseChoice::Quit);
+                Trans::Pop
+            }
+            None => Trans::None,
+        })
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        draw_world(&self.world, ctx, 1.0);
+
+        graphics::reset_transform_matrix(ctx);
+        let size = Vec2::new(window::get_width(ctx) as f32, window::get_height(ctx) as f32);
+        graphics::draw(ctx, &self.dim, DrawParams::new().scale(size).color(Color::rgba(0.0, 0.0, 0.0, 0.6)));
+        self.menu.draw(ctx, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
+
+/// Plays back a recorded run in place of live input
+struct ReplayState {
+    /// Shared with `PauseState` so it can keep drawing the replay underneath
+    world: Rc<World>,
+    replay: Replay,
+    frame: u64,
+    stats: RunStats,
+    timestep: FixedTimestep,
+    hud: Hud,
+}
+
+impl ReplayState {
+    fn new(ctx: &mut Context, res: &mut Res, replay: Replay) -> Result<ReplayState> {
+        Ok(ReplayState {
+            world: Rc::new(create_world(ctx, res, replay.seed)),
+            stats: RunStats::new(replay.seed),
+            replay,
+            frame: 0,
+            timestep: FixedTimestep::new(TICK_RATE),
+            hud: Hud::new(),
+        })
+    }
+}
+
+impl PDAState<Res> for ReplayState {
     fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
-        if input::is_key_down(ctx.input_context(), Key::Space) {
-            return Ok(Trans::Switch(Box::new(GameState::new(ctx, res)?)));
+        match res.pause_choice.take() {
+            Some(PauseChoice::Restart) => {
+                let replay = self.replay.clone();
+                return Ok(Trans::Switch(Box::new(ReplayState::new(ctx, res, replay)?)));
+            }
+            Some(PauseChoice::Quit) => return Ok(Trans::Pop),
+            None => { }
+        }
+
+        if res.controls.is_pressed(ctx.input_context(), Action::Pause) {
+            self.timestep.reset();
+            return Ok(Trans::Push(Box::new(PauseState::new(ctx, self.world.clone())?)));
         }
 
+        for _ in 0..self.timestep.advance() {
+            snapshot_transforms(&self.world);
+
+            let input = self.replay.frame(self.frame);
+            self.world.run(|mut game: UniqueViewMut<AsteroidGame>| {
+                input.apply(&mut game);
+            });
+            run_frame(&self.world);
+            self.stats.record(&self.world);
+            self.frame += 1;
+
+            if player_is_dead(&self.world) || self.frame >= self.replay.frames.len() as u64 {
+                // Replays can't post high scores
+                return Ok(Trans::Switch(Box::new(DeadState::new(res, self.stats, false))));
+            }
+        }
         Ok(Trans::None)
     }
 
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        draw_world(&self.world, ctx, self.timestep.alpha());
+        self.hud.draw(ctx, &self.world);
+
+        Ok(())
+    }
+}
+
+/// Initials being entered for a new high score
+struct InitialsEntry {
+    letters: [u8; highscores::INITIALS_LEN],
+    cursor: usize,
+}
+
+struct DeadState {
+    stats: RunStats,
+    initials: Option<InitialsEntry>,
+    summary: Text,
+    /// Only shown once any initials have been entered
+    menu: Menu,
+    table: Text,
+}
+
+impl DeadState {
+    fn new(res: &Res, stats: RunStats, can_post: bool) -> Self {
+        let score = stats.score;
+        let summary = Text::new(
+            format!(
+                "Score: {}\nTime survived: {}\nAsteroids destroyed: {} ({} split)\nEnemies destroyed: {}\nShots fired: {}\nAccuracy: {:.1}%\nSurvival bonus: {}\nSeed: {}",
+                score.total(),
+                stats.time_string(),
+                score.asteroids_destroyed,
+                score.asteroids_split,
+                score.enemies_destroyed,
+                stats.shots_fired,
+                stats.accuracy() * 100.0,
+                score.survival_bonus(),
+                stats.seed,
+            ),
+            Font::default(),
+            28.0,
+        );
+
+        let initials = if can_post && res.high_scores.qualifies(score.total()) {
+            Some(InitialsEntry {
+                letters: [b'A'; highscores::INITIALS_LEN],
+                cursor: 0,
+            })
+        } else {
+            None
+        };
+
+        let mut dead = DeadState {
+            stats,
+            initials,
+            summary,
+            menu: Menu::new("Game Over", vec![
+                String::from("Play again (same seed)"),
+                String::from("Play a new seed"),
+                String::from("Back to title"),
+            ]),
+            table: Text::new("", Font::default(), 24.0),
+        };
+        dead.refresh_table(res);
+        dead
+    }
+
+    fn refresh_table(&mut self, res: &Res) {
+        let mut content = match &self.initials {
+            Some(entry) => {
+                let mut name = String::new();
+                for (i, letter) in entry.letters.iter().enumerate() {
+                    if i == entry.cursor {
+                        name.push_str(&format!("[{}]", *letter as char));
+                    } else {
+                        name.push(*letter as char);
+                    }
+                }
+                format!(
+                    "New high score! Enter your initials: {}\n{}/{} to change, {}/{} to move, {} to save\n\n",
+                    name,
+                    res.controls.describe(Action::MoveUp),
+                    res.controls.describe(Action::MoveDown),
+                    res.controls.describe(Action::MoveLeft),
+                    res.controls.describe(Action::MoveRight),
+                    res.controls.describe(Action::Confirm),
+                )
+            }
+            None => String::new(),
+        };
+
+        content.push_str(&high_score_table(res));
+        self.table.set_content(content);
+    }
+
+    fn update_initials(&mut self, ctx: &mut Context, res: &mut Res) {
+        let input = ctx.input_context();
+        let pressed = |action| res.controls.is_pressed(input, action);
+        let (up, down, left, right, confirm) = (
+            pressed(Action::MoveUp),
+            pressed(Action::MoveDown),
+            pressed(Action::MoveLeft),
+            pressed(Action::MoveRight),
+            pressed(Action::Confirm),
+        );
+
+        let entry = match &mut self.initials {
+            Some(entry) => entry,
+            None => return,
+        };
+
+        let letter = &mut entry.letters[entry.cursor];
+        if up {
+            *letter = if *letter == b'Z' { b'A' } else { *letter + 1 };
+        } else if down {
+            *letter = if *letter == b'A' { b'Z' } else { *letter - 1 };
+        } else if left && entry.cursor > 0 {
+            entry.cursor -= 1;
+        } else if right && entry.cursor < highscores::INITIALS_LEN - 1 {
+            entry.cursor += 1;
+        } else if confirm {
+            let name = String::from_utf8_lossy(&entry.letters).into_owned();
+            let score = self.stats.score;
+            res.high_scores.insert(HighScoreEntry::new(name, score.total(), self.stats.seed, score.frames_survived));
+            if let Err(e) = res.high_scores.save(&res.high_scores_path) {
+                eprintln!("Failed to save high scores to {}: {}", res.high_scores_path.display(), e);
+            }
+            self.initials = None;
+        } else {
+            return;
+        }
+
+        self.refresh_table(res);
+    }
+}
+
+impl PDAState<Res> for DeadState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if self.initials.is_some() {
+            self.update_initials(ctx, res);
+            return Ok(Trans::None);
+        }
+
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
+
+        Ok(match self.menu.update(ctx, &res.controls) {
+            Some(0) => Trans::Switch(Box::new(GameState::with_seed(ctx, res, self.stats.seed)?)),
+            Some(1) => Trans::Switch(Box::new(GameState::with_seed(ctx, res, random_seed())?)),
+            // The title is always at the bottom of the stack
+            Some(_) => Trans::Pop,
+            None => Trans::None,
+        })
+    }
+
     fn draw(&mut self, ctx: &mut Context, _resources: &mut Res) -> Result {
         graphics::clear(ctx, Color::rgb(0.45, 0.65, 1.0));
+        if self.initials.is_none() {
+            self.menu.draw(ctx, Vec2::new(700.0, 48.0));
+        }
+        graphics::draw(ctx, &self.summary, Vec2::new(64.0, 48.0));
+        graphics::draw(ctx, &self.table, Vec2::new(64.0, 400.0));
 
         Ok(())
     }
 }
 
-fn create_wall(
-    data: (f64, f64, f64, f64),
-    drawables: NonSendSync<UniqueView<Drawables>>,
-    mut entities: EntitiesViewMut, 
-    mut sprites: ViewMut<Sprite>, 
-    mut physicses: ViewMut<Physics>, 
-    mut physics_world: UniqueViewMut<PhysicsWorld>, 
-    mut physics_bodies: ViewMut<PhysicsBody>,
-    mut transforms: ViewMut<Transform>, ) 
-    {
-        let (pos_x, pos_y, scale_x, scale_y) = data;
-
-        // Stationary square
-        let scale_calc = |s: f64| { (s / 1024.0 * 2.0) as f32 };
-        let square = entities.add_entity((&mut sprites, &mut physicses), (
-            Sprite::from_command(
-                DrawCommand::new(drawables.alias[textures::SQUARE])
-                .scale(Vec2::new(scale_calc(scale_x), scale_calc(scale_y)))
-                .origin(Vec2::new(512.0, 512.0))
-                .color(Color::BLACK)
-                .draw_layer(draw_layers::WALL)
-            ),
-            Physics::default(),
+/// Bottom of the state stack. Everything else is pushed on top of it and pops back down to it.
+struct TitleState {
+    menu: Menu,
+    /// Given on the command line, played as soon as the title is first updated
+    replay: Option<Replay>,
+}
+
+impl TitleState {
+    fn new(replay: Option<Replay>) -> Self {
+        TitleState {
+            menu: Menu::new("Asteroids", vec![
+                String::from("Play"),
+                String::from("Seeded Play"),
+                String::from("Replays"),
+                String::from("Options"),
+                String::from("High Scores"),
+                String::from("Quit"),
+            ]),
+            replay,
+        }
+    }
+}
+
+impl PDAState<Res> for TitleState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if let Some(replay) = self.replay.take() {
+            return Ok(Trans::Push(Box::new(ReplayState::new(ctx, res, replay)?)));
+        }
+
+        Ok(match self.menu.update(ctx, &res.controls) {
+            Some(0) => Trans::Push(Box::new(GameState::new(ctx, res)?)),
+            Some(1) => Trans::Push(Box::new(SeedEntryState::new())),
+            Some(2) => Trans::Push(Box::new(ReplayListState::new())),
+            Some(3) => Trans::Push(Box::new(OptionsState::new(res))),
+            Some(4) => Trans::Push(Box::new(HighScoresState::new(res))),
+            Some(_) => Trans::Quit,
+            None => Trans::None,
+        })
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        self.menu.draw(ctx, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
+
+/// Type in a seed to play
+struct SeedEntryState {
+    digits: String,
+    /// The last digit typed was dropped for making the seed too big
+    overflowed: bool,
+    text: Text,
+}
+
+impl SeedEntryState {
+    fn new() -> Self {
+        let mut entry = SeedEntryState {
+            digits: String::new(),
+            overflowed: false,
+            text: Text::new("", Font::default(), 32.0),
+        };
+        entry.refresh();
+        entry
+    }
+
+    fn refresh(&mut self) {
+        self.text.set_content(format!(
+            "Seeded Play\n\nSeed: {}_\n\nType a number then confirm to play{}",
+            self.digits,
+            if self.overflowed { format!("\nSeeds go up to {}", u64::MAX) } else { String::new() },
         ));
+    }
+}
 
-        physics_world.create_body(
-            &mut entities,
-            &mut physics_bodies,
-            square,
-            &mut transforms,
-            Transform::new(pos_x, pos_y),
-            CollisionBody::from_collider(Collider::half_extents(scale_x, scale_y, layers::WALL, 0))
-        );
-}
\ No newline at end of file
+impl PDAState<Res> for SeedEntryState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
+
+        let typed: String = input::get_text_input(ctx)
+            .unwrap_or("")
+            .chars()
+            .filter(|c| c.is_ascii_digit())
+            .collect();
+        // Editing typed text rather than a game action, so it isn't rebindable
+        let deleted = input::is_key_pressed(ctx.input_context(), Key::Backspace);
+
+        if !typed.is_empty() || deleted {
+            self.overflowed = false;
+            if deleted {
+                self.digits.pop();
+            }
+            for digit in typed.chars() {
+                self.digits.push(digit);
+                // Only keep digits that leave a seed that fits in a u64
+                if self.digits.parse::<u64>().is_err() {
+                    self.digits.pop();
+                    self.overflowed = true;
+                }
+            }
+            self.refresh();
+        }
+
+        if res.controls.is_pressed(ctx.input_context(), Action::Confirm) {
+            if let Ok(seed) = self.digits.parse() {
+                // Replaced rather than pushed over so dying pops straight back to the title
+                return Ok(Trans::Switch(Box::new(GameState::with_seed(ctx, res, seed)?)));
+            }
+        }
+
+        Ok(Trans::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        graphics::draw(ctx, &self.text, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
+
+/// Picks a recording out of `replays/` to watch
+struct ReplayListState {
+    paths: Vec<PathBuf>,
+    menu: Menu,
+}
+
+impl ReplayListState {
+    fn new() -> Self {
+        let mut paths: Vec<PathBuf> = fs::read_dir(REPLAY_DIR)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "replay"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Named `<seed>-<unix time>`, newest first
+        paths.sort_by_key(|path| {
+            let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+            std::cmp::Reverse(stem.rsplit('-').next().and_then(|t| t.parse::<u64>().ok()).unwrap_or(0))
+        });
+        paths.truncate(15);
+
+        let mut options: Vec<String> = paths
+            .iter()
+            .map(|path| path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default())
+            .collect();
+        options.push(String::from("Back"));
+
+        ReplayListState {
+            paths,
+            menu: Menu::new("Replays", options),
+        }
+    }
+}
+
+impl PDAState<Res> for ReplayListState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
+
+        let path = match self.menu.update(ctx, &res.controls) {
+            Some(i) if i < self.paths.len() => &self.paths[i],
+            Some(_) => return Ok(Trans::Pop),
+            None => return Ok(Trans::None),
+        };
+
+        match Replay::load(path) {
+            Ok(replay) => Ok(Trans::Switch(Box::new(ReplayState::new(ctx, res, replay)?))),
+            Err(e) => {
+                eprintln!("Failed to load replay {}: {}", path.display(), e);
+                Ok(Trans::None)
+            }
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        self.menu.draw(ctx, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
+
+struct OptionsState {
+    menu: Menu,
+}
+
+impl OptionsState {
+    fn new(res: &Res) -> Self {
+        OptionsState {
+            menu: Menu::new("Options", OptionsState::options(res)),
+        }
+    }
+
+    fn options(res: &Res) -> Vec<String> {
+        vec![
+            format!("Fullscreen: {}", if res.fullscreen { "On" } else { "Off" }),
+            format!("Input: {}", match res.controls.device {
+                InputDevice::KeyboardMouse => "Keyboard & Mouse",
+                InputDevice::Gamepad => "Gamepad",
+            }),
+            String::from("Controls"),
+            String::from("Back"),
+        ]
+    }
+}
+
+impl PDAState<Res> for OptionsState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
+
+        match self.menu.update(ctx, &res.controls) {
+            Some(0) => {
+                res.fullscreen = !res.fullscreen;
+                window::set_fullscreen(ctx, res.fullscreen)?;
+                self.menu.set_options(OptionsState::options(res));
+            }
+            Some(1) => {
+                res.controls.device = match res.controls.device {
+                    InputDevice::KeyboardMouse => InputDevice::Gamepad,
+                    InputDevice::Gamepad => InputDevice::KeyboardMouse,
+                };
+                ControlsState::save(res);
+                self.menu.set_options(OptionsState::options(res));
+            }
+            Some(2) => return Ok(Trans::Push(Box::new(ControlsState::new(res)))),
+            Some(_) => return Ok(Trans::Pop),
+            None => { }
+        }
+
+        Ok(Trans::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        self.menu.draw(ctx, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
+
+/// Lists every action's bindings, picking one waits for the next key or button to bind to it
+struct ControlsState {
+    menu: Menu,
+    waiting: Option<Action>,
+    prompt: Text,
+}
+
+impl ControlsState {
+    fn new(res: &Res) -> Self {
+        ControlsState {
+            menu: Menu::new("Controls", ControlsState::options(res)),
+            waiting: None,
+            prompt: Text::new("", Font::default(), 28.0),
+        }
+    }
+
+    fn options(res: &Res) -> Vec<String> {
+        let mut options: Vec<String> = Action::ALL
+            .iter()
+            .map(|action| format!("{:?}: {}", action, res.controls.describe(*action)))
+            .collect();
+        options.push(String::from("Reset to defaults"));
+        options.push(String::from("Back"));
+        options
+    }
+
+    fn save(res: &Res) {
+        if let Err(e) = res.controls.save(&res.controls_path) {
+            eprintln!("Failed to save controls to {}: {}", res.controls_path.display(), e);
+        }
+    }
+}
+
+impl PDAState<Res> for ControlsState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if let Some(action) = self.waiting {
+            // Always cancels, so a rebind can be backed out of whatever Pause is bound to
+            if input::is_key_pressed(ctx.input_context(), Key::Escape) {
+                self.waiting = None;
+                self.prompt.set_content("");
+                return Ok(Trans::None);
+            }
+
+            if let Some(binding) = pressed_binding(ctx.input_context()) {
+                res.controls.rebind(action, binding);
+                ControlsState::save(res);
+                self.waiting = None;
+                self.prompt.set_content("");
+                self.menu.set_options(ControlsState::options(res));
+            }
+            return Ok(Trans::None);
+        }
+
+        if backed_out(ctx, &res.controls) {
+            return Ok(Trans::Pop);
+        }
+
+        match self.menu.update(ctx, &res.controls) {
+            Some(i) if i < Action::ALL.len() => {
+                self.waiting = Some(Action::ALL[i]);
+                self.prompt.set_content(format!("Press a key or button for {:?}, Escape to cancel", Action::ALL[i]));
+            }
+            Some(i) if i == Action::ALL.len() => {
+                res.controls = ActionMap::default();
+                ControlsState::save(res);
+                self.menu.set_options(ControlsState::options(res));
+            }
+            Some(_) => return Ok(Trans::Pop),
+            None => { }
+        }
+
+        Ok(Trans::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        self.menu.draw(ctx, Vec2::new(64.0, 64.0));
+        graphics::draw(ctx, &self.prompt, Vec2::new(64.0, 500.0));
+
+        Ok(())
+    }
+}
+
+struct HighScoresState {
+    table: Text,
+}
+
+impl HighScoresState {
+    fn new(res: &Res) -> Self {
+        HighScoresState {
+            table: Text::new(high_score_table(res), Font::default(), 28.0),
+        }
+    }
+}
+
+impl PDAState<Res> for HighScoresState {
+    fn update(&mut self, ctx: &mut Context, res: &mut Res) -> Result<Trans<Res>> {
+        if backed_out(ctx, &res.controls) || res.controls.is_pressed(ctx.input_context(), Action::Confirm) {
+            return Ok(Trans::Pop);
+        }
+
+        Ok(Trans::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, _: &mut Res) -> Result {
+        graphics::clear(ctx, Color::rgb(0.392, 0.584, 0.929));
+        graphics::draw(ctx, &self.table, Vec2::new(64.0, 64.0));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use vermarine_lib::{
+    shipyard::*,
+    tetra::graphics::Color,
+    physics::{
+        PhysicsBody,
+        world::{
+            PhysicsWorld,
+        },
+    },
+    components::{
+        Transform,
+    },
+    rendering::{
+        Sprite,
+    },
+};
+
+use rand::rngs::StdRng;
+use rand::{
+    Rng,
+    SeedableRng,
+};
+
+use crate::{
+    components::*,
+    events::{
+        Events,
+        GameEvent,
+    },
+    draw_layers,
+    AsteroidGame,
+    SpriteTextures,
+};
+
+/// Past this many live particles new bursts are skipped
+const MAX_PARTICLES: usize = 1000;
+
+//
+// Particle
+//
+// Drifts and slows down while its `Fade` runs out. Has no physics body so it never collides.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Particle {
+    pub dx: f64,
+    pub dy: f64,
+    /// Fraction of its speed kept each frame
+    pub drag: f64,
+}
+
+//
+// Burst presets
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Burst {
+    pub count: u32,
+    /// Picked uniformly from `(min, max)`
+    pub speed: (f64, f64),
+    pub life: (i32, i32),
+    pub radius: f64,
+    pub color: Color,
+    pub grow: f32,
+    pub drag: f64,
+    /// Degrees either side of `direction` particles fly off at, 180 for all round
+    pub arc: f64,
+}
+
+impl Burst {
+    pub fn explosion(radius: f64) -> Self {
+        Burst {
+            count: (radius / 4.0).max(6.0) as u32,
+            speed: (2.0, 6.0),
+            life: (20, 40),
+            radius: 4.0,
+            color: Color::rgb(1.0, 0.6, 0.2),
+            grow: 0.97,
+            drag: 0.93,
+            arc: 180.0,
+        }
+    }
+
+    pub fn debris(radius: f64) -> Self {
+        Burst {
+            count: (radius / 10.0).max(3.0) as u32,
+            speed: (1.0, 4.0),
+            life: (15, 30),
+            radius: 3.0,
+            color: Color::rgb(0.5, 0.5, 0.5),
+            grow: 0.98,
+            drag: 0.95,
+            arc: 180.0,
+        }
+    }
+
+    pub fn impact() -> Self {
+        Burst {
+            count: 4,
+            speed: (1.0, 3.0),
+            life: (8, 14),
+            radius: 2.5,
+            color: Color::rgb(0.6, 0.8, 1.0),
+            grow: 0.95,
+            drag: 0.9,
+            arc: 180.0,
+        }
+    }
+
+    pub fn hit() -> Self {
+        Burst {
+            count: 12,
+            speed: (3.0, 7.0),
+            life: (15, 25),
+            radius: 3.0,
+            color: Color::RED,
+            grow: 0.96,
+            drag: 0.9,
+            arc: 180.0,
+        }
+    }
+
+    pub fn thrust() -> Self {
+        Burst {
+            count: 1,
+            speed: (1.5, 3.0),
+            life: (10, 18),
+            radius: 3.0,
+            color: Color::rgb(1.0, 0.8, 0.3),
+            grow: 0.93,
+            drag: 0.95,
+            arc: 20.0,
+        }
+    }
+}
+
+//
+// ParticleRng unique
+//
+// Kept apart from the game's `StdRng` so tweaking effects never changes how a seed or replay plays out
+pub struct ParticleRng(pub StdRng);
+
+impl ParticleRng {
+    pub fn new(seed: u64) -> Self {
+        ParticleRng(StdRng::seed_from_u64(seed))
+    }
+}
+
+fn roll<T: rand::distributions::uniform::SampleUniform + PartialOrd + Copy>(rand: &mut StdRng, (min, max): (T, T)) -> T {
+    if min < max {
+        rand.gen_range(min, max)
+    } else {
+        min
+    }
+}
+
+/// Spawns `burst` at (`x`, `y`) flying off around `direction` degrees
+pub fn spawn_burst(all_storages: &AllStorages, burst: Burst, x: f64, y: f64, direction: f64) {
+    all_storages.run(|
+        textures: UniqueView<SpriteTextures>,
+        mut rand: UniqueViewMut<ParticleRng>,
+        mut entities: EntitiesViewMut,
+        mut transforms: ViewMut<Transform>,
+        mut sprites: ViewMut<Sprite>,
+        mut fades: ViewMut<Fade>,
+        mut particles: ViewMut<Particle>,| {
+            if particles.iter().count() >= MAX_PARTICLES {
+                return;
+            }
+
+            for _ in 0..burst.count {
+                let angle = (direction + roll(&mut rand.0, (-burst.arc, burst.arc))).to_radians();
+                let speed = roll(&mut rand.0, burst.speed);
+                let life = roll(&mut rand.0, burst.life);
+
+                entities.add_entity(
+                    (
+                        &mut transforms,
+                        &mut sprites,
+                        &mut fades,
+                        &mut particles,
+                    ),
+                    (
+                        Transform::new(x, y),
+                        create_sprite(textures.asteroid, burst.radius, burst.color, draw_layers::PARTICLE),
+                        Fade::new(life, burst.grow),
+                        Particle {
+                            dx: angle.sin() * speed,
+                            dy: -angle.cos() * speed,
+                            drag: burst.drag,
+                        },
+                    ),
+                );
+            }
+    });
+}
+
+/// Turns this frame's events, and the player's movement, into bursts
+pub fn emit_particles(all_storages: AllStoragesViewMut) {
+    let mut bursts = vec![];
+
+    {
+        let (events, game, players, physics_bodies, physics_world) = all_storages
+            .borrow::<(
+                UniqueView<Events>,
+                UniqueView<AsteroidGame>,
+                View<Player>,
+                View<PhysicsBody>,
+                UniqueView<PhysicsWorld>,
+            )>();
+
+        for event in events.iter() {
+            match *event {
+                GameEvent::AsteroidSplit { x, y, radius } => bursts.push((Burst::debris(radius), x, y, 0.0)),
+                GameEvent::AsteroidDestroyed { x, y, radius } => bursts.push((Burst::explosion(radius), x, y, 0.0)),
+                GameEvent::EnemyDestroyed { x, y, .. } => bursts.push((Burst::explosion(40.0), x, y, 0.0)),
+                GameEvent::BulletBounced { x, y } | GameEvent::BulletDestroyed { x, y } => bursts.push((Burst::impact(), x, y, 0.0)),
+                GameEvent::PlayerHit { x, y, .. } => bursts.push((Burst::hit(), x, y, 0.0)),
+                _ => { }
+            }
+        }
+
+        let (mut move_x, mut move_y) = game.move_axis.unwrap_or((0.0, 0.0));
+        if game.move_left { move_x -= 1.0; }
+        if game.move_right { move_x += 1.0; }
+        if game.move_up { move_y -= 1.0; }
+        if game.move_down { move_y += 1.0; }
+
+        if move_x != 0.0 || move_y != 0.0 {
+            if let Some((id, _)) = (&physics_bodies, &players).iter().with_id().next() {
+                let transform = physics_world.transform(id);
+                // Out the back, opposite to the way the player is going
+                let direction = (-move_x).atan2(move_y).to_degrees();
+                bursts.push((Burst::thrust(), transform.x, transform.y, direction));
+            }
+        }
+    }
+
+    for (burst, x, y, direction) in bursts.into_iter() {
+        spawn_burst(&all_storages, burst, x, y, direction);
+    }
+}
+
+pub fn move_particles(mut particles: ViewMut<Particle>, mut transforms: ViewMut<Transform>) {
+    for (particle, transform) in (&mut particles, &mut transforms).iter() {
+        transform.x += particle.dx;
+        transform.y += particle.dy;
+        particle.dx *= particle.drag;
+        particle.dy *= particle.drag;
+    }
+}
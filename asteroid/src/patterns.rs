@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::components::ProjectileType;
+
+//
+// Bullet pattern language
+//
+// Written in RON as a list of patterns inside an archetype's `firing` field, e.g. a spiral:
+//   [Ring(count: 4, shot: (speed: 3.0)), Rotate(16.0), Wait(5)]
+// The whole list loops for as long as the enemy is alive, so it has to `Wait` somewhere.
+// Angles are degrees relative to the emitter's current direction.
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Shot {
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    #[serde(default)]
+    pub accel: f64,
+    #[serde(default)]
+    pub curve: f64,
+    #[serde(default = "default_radius")]
+    pub radius: f64,
+    #[serde(default = "default_color")]
+    pub color: (f32, f32, f32),
+    /// Bouncing and piercing, e.g. `projectile: (bounce_limit: 2, bounce_speed: 0.8)`. Doesn't bounce by default.
+    #[serde(default)]
+    pub projectile: ProjectileType,
+}
+
+fn default_speed() -> f64 { 3.0 }
+fn default_radius() -> f64 { 7.5 }
+fn default_color() -> (f32, f32, f32) { (0.8, 0.0, 0.0) }
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub enum Pattern {
+    /// A single bullet `angle` degrees off the current direction
+    Fire { #[serde(default)] angle: f64, shot: Shot },
+    /// `count` bullets spaced evenly around a full circle
+    Ring { count: u32, shot: Shot },
+    /// `count` bullets spread evenly across `arc` degrees, centred on the current direction
+    Spread { count: u32, arc: f64, shot: Shot },
+    /// Point the emitter at the player
+    Aim,
+    /// Turn the emitter by this many degrees
+    Rotate(f64),
+    /// Carry on this many frames later
+    Wait(u32),
+    /// Run `body` `times` times
+    Repeat { times: u32, body: Vec<Pattern> },
+}
+
+//
+// Compiled form, a flat list so the interpreter only has to keep a program counter and a loop stack
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// Bullets at each offset from the current direction
+    Emit { offsets: Vec<f64>, shot: Shot },
+    Aim,
+    Rotate(f64),
+    Wait(u32),
+    /// Jumps past `end` when `times` is zero
+    RepeatStart { times: u32, end: usize },
+    RepeatEnd,
+}
+
+pub type Program = Vec<Op>;
+
+/// Rejects programs that never wait, they'd fire every frame forever
+pub fn compile(patterns: &[Pattern]) -> Result<Program, String> {
+    if !waits(patterns) {
+        return Err(String::from("firing never waits, it needs a Wait of at least 1 frame"));
+    }
+
+    let mut ops = vec![];
+    compile_into(patterns, &mut ops);
+    Ok(ops)
+}
+
+/// Whether running `patterns` once is sure to pass a `Wait` of a frame or more
+fn waits(patterns: &[Pattern]) -> bool {
+    patterns.iter().any(|pattern| match pattern {
+        Pattern::Wait(frames) => *frames > 0,
+        Pattern::Repeat { times, body } => *times > 0 && waits(body),
+        _ => false,
+    })
+}
+
+fn compile_into(patterns: &[Pattern], ops: &mut Vec<Op>) {
+    for pattern in patterns.iter() {
+        match pattern {
+            Pattern::Fire { angle, shot } => ops.push(Op::Emit { offsets: vec![*angle], shot: shot.clone() }),
+            Pattern::Ring { count, shot } => {
+                let step = 360.0 / (*count).max(1) as f64;
+                let offsets = (0..*count).map(|i| i as f64 * step).collect();
+                ops.push(Op::Emit { offsets, shot: shot.clone() });
+            }
+            Pattern::Spread { count, arc, shot } => {
+                let offsets = if *count <= 1 {
+                    vec![0.0; *count as usize]
+                } else {
+                    let step = arc / (*count - 1) as f64;
+                    (0..*count).map(|i| -arc / 2.0 + i as f64 * step).collect()
+                };
+                ops.push(Op::Emit { offsets, shot: shot.clone() });
+            }
+            Pattern::Aim => ops.push(Op::Aim),
+            Pattern::Rotate(degrees) => ops.push(Op::Rotate(*degrees)),
+            Pattern::Wait(frames) => ops.push(Op::Wait(*frames)),
+            Pattern::Repeat { times, body } => {
+                let start = ops.len();
+                ops.push(Op::RepeatStart { times: *times, end: 0 });
+                compile_into(body, ops);
+                let end = ops.len();
+                ops.push(Op::RepeatEnd);
+                ops[start] = Op::RepeatStart { times: *times, end };
+            }
+        }
+    }
+}
+
+/// Stops a huge `Repeat` from stalling a frame
+const MAX_OPS_PER_FRAME: u32 = 256;
+
+//
+// BulletEmitter
+//
+// Runs a compiled pattern program, one step per frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct BulletEmitter {
+    pub program: Arc<Program>,
+    /// Current direction in degrees
+    pub angle: f64,
+    pc: usize,
+    wait: u32,
+    /// (first op of the loop body, repeats still to go)
+    loops: Vec<(usize, u32)>,
+}
+
+impl BulletEmitter {
+    pub fn new(program: Arc<Program>, angle: f64) -> Self {
+        BulletEmitter {
+            program,
+            angle,
+            pc: 0,
+            wait: 0,
+            loops: vec![],
+        }
+    }
+
+    /// Advances the program by a frame, pushing an (angle, shot) for every bullet fired.
+    /// `aim` is the angle to the player if there is one.
+    pub fn step(&mut self, aim: Option<f64>, out: &mut Vec<(f64, Shot)>) {
+        if self.program.is_empty() {
+            return;
+        }
+
+        if self.wait > 0 {
+            self.wait -= 1;
+            return;
+        }
+
+        let program = self.program.clone();
+        let mut executed = 0;
+        while executed < MAX_OPS_PER_FRAME {
+            if self.pc >= program.len() {
+                // Loop the whole program, starting over next frame if anything ran this one
+                self.pc = 0;
+                self.loops.clear();
+                if executed > 0 {
+                    return;
+                }
+            }
+
+            executed += 1;
+            let op = &program[self.pc];
+            self.pc += 1;
+
+            match op {
+                Op::Emit { offsets, shot } => {
+                    for offset in offsets.iter() {
+                        out.push((self.angle + offset, shot.clone()));
+                    }
+                }
+                Op::Aim => {
+                    if let Some(aim) = aim {
+                        self.angle = aim;
+                    }
+                }
+                Op::Rotate(degrees) => self.angle += degrees,
+                Op::Wait(frames) => {
+                    if *frames > 0 {
+                        self.wait = frames - 1;
+                        return;
+                    }
+                }
+                Op::RepeatStart { times, end } => {
+                    if *times == 0 {
+                        self.pc = end + 1;
+                    } else {
+                        self.loops.push((self.pc, times - 1));
+                    }
+                }
+                Op::RepeatEnd => {
+                    if let Some((start, remaining)) = self.loops.last_mut() {
+                        if *remaining > 0 {
+                            *remaining -= 1;
+                            self.pc = *start;
+                        } else {
+                            self.loops.pop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shot() -> Shot {
+        ron::de::from_str("()").unwrap()
+    }
+
+    fn run(patterns: &[Pattern], frames: u32) -> Vec<usize> {
+        let mut emitter = BulletEmitter::new(Arc::new(compile(patterns).unwrap()), 0.0);
+        (0..frames)
+            .map(|_| {
+                let mut out = vec![];
+                emitter.step(None, &mut out);
+                out.len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rejects_programs_that_never_wait() {
+        assert!(compile(&[Pattern::Fire { angle: 0.0, shot: shot() }]).is_err());
+        assert!(compile(&[Pattern::Fire { angle: 0.0, shot: shot() }, Pattern::Wait(0)]).is_err());
+        assert!(compile(&[Pattern::Repeat { times: 0, body: vec![Pattern::Wait(5)] }]).is_err());
+        assert!(compile(&[Pattern::Repeat { times: 2, body: vec![Pattern::Wait(5)] }]).is_ok());
+    }
+
+    #[test]
+    fn waits_between_volleys() {
+        let patterns = [Pattern::Ring { count: 4, shot: shot() }, Pattern::Wait(3)];
+        assert_eq!(run(&patterns, 7), vec![4, 0, 0, 4, 0, 0, 4]);
+    }
+
+    #[test]
+    fn repeats_run_their_body() {
+        let patterns = [
+            Pattern::Repeat { times: 2, body: vec![Pattern::Fire { angle: 0.0, shot: shot() }, Pattern::Wait(1)] },
+            Pattern::Wait(2),
+        ];
+        assert_eq!(run(&patterns, 6), vec![1, 1, 0, 0, 1, 1]);
+    }
+}
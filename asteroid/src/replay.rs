@@ -0,0 +1,301 @@
+use std::{
+    fs,
+    io::{
+        self,
+        ErrorKind,
+    },
+    path::Path,
+};
+
+use crate::input::InputFrame;
+
+const MAGIC: &[u8; 4] = b"ASTR";
+const VERSION: u8 = 3;
+
+const MOVE_LEFT: u8 = 1;
+const MOVE_RIGHT: u8 = 1 << 1;
+const MOVE_UP: u8 = 1 << 2;
+const MOVE_DOWN: u8 = 1 << 3;
+const LMB_DOWN: u8 = 1 << 4;
+const ANALOG: u8 = 1 << 5;
+
+//
+// Replay
+//
+// File layout (little endian):
+//   magic "ASTR", version u8, seed u64, frame count u32,
+//   then per frame a flags byte, followed by the shoot angle as an f64 only when firing,
+//   then the stick's x and y as f64s only when moving with one.
+//   `shoot_angle` is only ever read while `lmb_down` is held so other frames don't need it.
+//   Version 2 files stored the stick as f32s, which doesn't play back exactly, and
+//   version 1 files are the same without analog movement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub frames: Vec<InputFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Self {
+        Replay {
+            seed,
+            frames: vec![],
+        }
+    }
+
+    pub fn record(&mut self, frame: InputFrame) {
+        self.frames.push(frame);
+    }
+
+    /// Input for `frame`, or no input at all once the recording has run out
+    pub fn frame(&self, frame: u64) -> InputFrame {
+        self.frames.get(frame as usize).copied().unwrap_or_default()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(17 + self.frames.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+
+        for frame in self.frames.iter() {
+            let mut flags = 0;
+            if frame.move_left { flags |= MOVE_LEFT; }
+            if frame.move_right { flags |= MOVE_RIGHT; }
+            if frame.move_up { flags |= MOVE_UP; }
+            if frame.move_down { flags |= MOVE_DOWN; }
+            if frame.lmb_down { flags |= LMB_DOWN; }
+            if frame.move_axis.is_some() { flags |= ANALOG; }
+
+            bytes.push(flags);
+            if frame.lmb_down {
+                bytes.extend_from_slice(&frame.shoot_angle.to_le_bytes());
+            }
+            if let Some((x, y)) = frame.move_axis {
+                bytes.extend_from_slice(&x.to_le_bytes());
+                bytes.extend_from_slice(&y.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err(invalid("not a replay file"));
+        }
+        let version = reader.take(1)?[0];
+        if version == 0 || version > VERSION {
+            return Err(invalid("unsupported replay version"));
+        }
+
+        let seed = u64::from_le_bytes(reader.array8()?);
+        let mut count = [0u8; 4];
+        count.copy_from_slice(reader.take(4)?);
+        let count = u32::from_le_bytes(count);
+
+        // Every frame is at least a flags byte, so a bad count can't ask for more than the file holds
+        let mut frames = Vec::with_capacity((count as usize).min(reader.remaining()));
+        for _ in 0..count {
+            let flags = reader.take(1)?[0];
+            let lmb_down = flags & LMB_DOWN > 0;
+            let shoot_angle = if lmb_down {
+                f64::from_le_bytes(reader.array8()?)
+            } else {
+                0f64
+            };
+            let move_axis = if flags & ANALOG > 0 && version == 2 {
+                let x = f32::from_le_bytes(reader.array4()?);
+                let y = f32::from_le_bytes(reader.array4()?);
+                Some((x as f64, y as f64))
+            } else if flags & ANALOG > 0 {
+                let x = f64::from_le_bytes(reader.array8()?);
+                let y = f64::from_le_bytes(reader.array8()?);
+                Some((x, y))
+            } else {
+                None
+            };
+
+            frames.push(InputFrame {
+                move_left: flags & MOVE_LEFT > 0,
+                move_right: flags & MOVE_RIGHT > 0,
+                move_up: flags & MOVE_UP > 0,
+                move_down: flags & MOVE_DOWN > 0,
+                move_axis,
+                lmb_down,
+                shoot_angle,
+            });
+        }
+
+        Ok(Replay { seed, frames })
+    }
+
+    /// Written alongside and then moved into place, so saving over a recording mid-run
+    /// never leaves it half written
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp = path.with_extension("replay.tmp");
+        fs::write(&temp, self.to_bytes())?;
+        fs::rename(&temp, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Replay::from_bytes(&fs::read(path)?)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.pos + len > self.bytes.len() {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "replay file is truncated"));
+        }
+        let slice = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn array8(&mut self) -> io::Result<[u8; 8]> {
+        let mut array = [0u8; 8];
+        array.copy_from_slice(self.take(8)?);
+        Ok(array)
+    }
+
+    fn array4(&mut self) -> io::Result<[u8; 4]> {
+        let mut array = [0u8; 4];
+        array.copy_from_slice(self.take(4)?);
+        Ok(array)
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(0xDEAD_BEEF_1234);
+        replay.record(InputFrame::default());
+        replay.record(InputFrame {
+            move_left: true,
+            move_up: true,
+            ..InputFrame::default()
+        });
+        replay.record(InputFrame {
+            move_right: true,
+            lmb_down: true,
+            shoot_angle: 123.456,
+            ..InputFrame::default()
+        });
+        replay.record(InputFrame {
+            move_axis: Some((0.25, -0.75)),
+            ..InputFrame::default()
+        });
+        // Not exact as an f32, so this only survives if the full f64 is kept
+        replay.record(InputFrame {
+            move_axis: Some((0.1, 1.0 / 3.0)),
+            ..InputFrame::default()
+        });
+        replay.record(InputFrame {
+            move_down: true,
+            move_axis: Some((-1.0, 0.5)),
+            lmb_down: true,
+            shoot_angle: -90.0,
+            ..InputFrame::default()
+        });
+        replay
+    }
+
+    fn header(version: u8, count: u32) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(version);
+        bytes.extend_from_slice(&42u64.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let replay = sample();
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+    }
+
+    #[test]
+    fn round_trip_empty() {
+        let replay = Replay::new(7);
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()).unwrap(), replay);
+    }
+
+    #[test]
+    fn reads_version_1() {
+        let mut bytes = header(1, 2);
+        bytes.push(MOVE_UP);
+        bytes.push(LMB_DOWN);
+        bytes.extend_from_slice(&45f64.to_le_bytes());
+
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(replay.seed, 42);
+        assert_eq!(replay.frames, vec![
+            InputFrame { move_up: true, ..InputFrame::default() },
+            InputFrame { lmb_down: true, shoot_angle: 45.0, ..InputFrame::default() },
+        ]);
+    }
+
+    #[test]
+    fn reads_version_2_sticks() {
+        let mut bytes = header(2, 1);
+        bytes.push(ANALOG);
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-0.25f32).to_le_bytes());
+
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(replay.frames, vec![InputFrame { move_axis: Some((0.5, -0.25)), ..InputFrame::default() }]);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = sample().to_bytes();
+        bytes[0] = b'X';
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut bytes = sample().to_bytes();
+        bytes[4] = VERSION + 1;
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let bytes = sample().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(Replay::from_bytes(&bytes[..len]).is_err(), "accepted {} of {} bytes", len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn rejects_huge_count() {
+        let mut bytes = header(VERSION, u32::MAX);
+        bytes.push(0);
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+}
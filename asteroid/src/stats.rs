@@ -0,0 +1,66 @@
+use vermarine_lib::shipyard::*;
+
+use crate::{
+    components::*,
+    events::{
+        Events,
+        GameEvent,
+    },
+    timestep::TICK_RATE,
+};
+
+//
+// Run stats
+//
+// Totted up from each tick's events by whoever is running the world, then handed to the game over screen
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RunStats {
+    pub seed: u64,
+    pub score: Score,
+    pub shots_fired: u64,
+    /// Player bullets that hit at least one thing
+    pub shots_landed: u64,
+}
+
+impl RunStats {
+    pub fn new(seed: u64) -> Self {
+        RunStats {
+            seed,
+            ..RunStats::default()
+        }
+    }
+
+    /// Call after every tick
+    pub fn record(&mut self, world: &World) {
+        world.run(|events: UniqueView<Events>, score: UniqueView<Score>| {
+            for event in events.iter() {
+                match *event {
+                    GameEvent::ShotFired { count, .. } => self.shots_fired += count as u64,
+                    GameEvent::BulletLanded { team: Team::Player, .. } => self.shots_landed += 1,
+                    _ => { }
+                }
+            }
+            self.score = *score;
+        });
+    }
+
+    /// Fraction of shots fired that landed, 0 when nothing was fired
+    pub fn accuracy(&self) -> f64 {
+        if self.shots_fired == 0 {
+            return 0.0;
+        }
+        self.shots_landed as f64 / self.shots_fired as f64
+    }
+
+    /// Time survived as `m:ss`
+    pub fn time_string(&self) -> String {
+        time_string(self.score.frames_survived)
+    }
+}
+
+/// `frames` ticks as `m:ss`
+pub fn time_string(frames: u64) -> String {
+    let secs = (frames as f64 / TICK_RATE) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
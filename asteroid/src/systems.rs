@@ -21,7 +21,6 @@ use vermarine_lib::{
     },
     rendering::{
         Sprite,
-        Drawables,
     },
 };
 
@@ -29,10 +28,35 @@ use rand::rngs::StdRng;
 use rand::Rng;
 
 use crate::{
+    archetypes::{
+        rgb,
+        spawn_archetype,
+        Archetypes,
+    },
+    arena::Arena,
+    camera::CameraController,
     components::*,
+    damage::{
+        find_rule,
+        Damage,
+        DamageQueue,
+        Death,
+        OnDeath,
+    },
+    powerups,
+    events::{
+        Events,
+        GameEvent,
+    },
+    director::{
+        Director,
+        Edge,
+        Formation,
+    },
+    patterns::BulletEmitter,
     layers,
-    textures,
     AsteroidGame,
+    SpriteTextures,
     draw_layers,
 };
 
@@ -58,7 +82,8 @@ pub fn apply_physics(mut physics_bodies: ViewMut<PhysicsBody>, mut physicses: Vi
     }
 }
 
-pub fn move_player_bullets(
+pub fn move_bullets(
+    mut events: UniqueViewMut<Events>,
     mut physics_bodies: ViewMut<PhysicsBody>,
     mut physics_world: UniqueViewMut<PhysicsWorld>,
     mut physicses: ViewMut<Physics>,
@@ -67,7 +92,7 @@ pub fn move_player_bullets(
     physics_world.sync(&mut physics_bodies);
 
     for (id, (_, physics, bullet)) in (&physics_bodies, &mut physicses, &mut bullets).iter().with_id() {
-        if physics.apply_auto || bullet.team != Team::Player {
+        if physics.apply_auto {
             continue;
         }
 
@@ -89,35 +114,40 @@ pub fn move_player_bullets(
             }
 
             bullet.bounces += 1;
-
-            physics.dx = reflected.x;
-            physics.dy = reflected.y;
-            physics.angle = 0.0;
-            physics.speed = 0.0;
-            physics.accel = 0.0;
-            physics.curve = 0.0;
+            let transform = physics_world.transform(id);
+            events.emit(GameEvent::BulletBounced { x: transform.x, y: transform.y });
+
+            // Carry on in angle/speed form so curve and accel still make sense after the bounce
+            let projectile = bullet.projectile;
+            physics.dx = 0.0;
+            physics.dy = 0.0;
+            physics.angle = reflected.x.atan2(-reflected.y).to_degrees();
+            physics.speed = reflected.magnitude() * projectile.bounce_speed;
+            if !projectile.keep_accel {
+                physics.accel = 0.0;
+            }
+            if !projectile.keep_curve {
+                physics.curve = 0.0;
+            }
         }
-    } 
+    }
 }
 
-pub fn wrap_asteroids(mut physics_bodies: ViewMut<PhysicsBody>, asteroids: View<Asteroid>, mut physics_world: UniqueViewMut<PhysicsWorld>) {
+pub fn wrap_asteroids(arena: UniqueView<Arena>, mut physics_bodies: ViewMut<PhysicsBody>, asteroids: View<Asteroid>, mut physics_world: UniqueViewMut<PhysicsWorld>) {
     physics_world.sync(&mut physics_bodies);
     
     for (id, _) in (&physics_bodies, &asteroids).iter().with_id() {
-        wrap_body(&mut physics_world, id);
+        wrap_body(&arena, &mut physics_world, id);
     }
 }
 
-pub fn wrap_body(physics_world: &mut UniqueViewMut<PhysicsWorld>, id: EntityId) {
+pub fn wrap_body(arena: &Arena, physics_world: &mut UniqueViewMut<PhysicsWorld>, id: EntityId) {
     let (t, collision_body) = physics_world.parts(id);
     let r = collision_body.sensors[0].shape.get_width() / 2.0;
 
     let buffer = 20.0;
 
-    let left = -1300.0;
-    let right = 1300.0;
-    let top = -800.0;
-    let bottom = 800.0;
+    let (left, right, top, bottom) = arena.spawn_bounds();
 
     // Wrap X
     if t.x > right + r + buffer {
@@ -143,15 +173,12 @@ pub fn destroy_offscreen(mut all_storages: AllStoragesViewMut) {
     let mut deferred = vec![];
 
     {
-        let (mut physics_bodies, mut physics_world) = all_storages.borrow::<(ViewMut<PhysicsBody>, UniqueViewMut<PhysicsWorld>)>();
+        let (arena, mut physics_bodies, mut physics_world) = all_storages.borrow::<(UniqueView<Arena>, ViewMut<PhysicsBody>, UniqueViewMut<PhysicsWorld>)>();
         physics_world.sync(&mut physics_bodies);
 
         for (e, _) in (&physics_bodies).iter().with_id().filter(|(e, _)| {
             let transform = physics_world.transform(*e);
-            transform.x < -2000.0
-                || transform.x > 2000.0
-                || transform.y < -1500.0
-                || transform.y > 1500.0
+            arena.is_culled(transform.x, transform.y)
         }) {
             deferred.push(e);
         }
@@ -162,194 +189,97 @@ pub fn destroy_offscreen(mut all_storages: AllStoragesViewMut) {
     }
 }
 
-pub fn spawn_asteroids(
-    drawables: NonSendSync<UniqueView<Drawables>>,
-    mut entities: EntitiesViewMut,
-    mut rand: UniqueViewMut<StdRng>,
-    mut game: UniqueViewMut<AsteroidGame>,
-    mut physicses: ViewMut<Physics>,
-    mut sprites: ViewMut<Sprite>,
-    mut asteroids: ViewMut<Asteroid>,
-    mut physics_bodies: ViewMut<PhysicsBody>,
-    mut physics_world: UniqueViewMut<PhysicsWorld>,
-    mut transforms: ViewMut<Transform>,
-) {
-    game.asteroid_timer += 1;
-    while game.asteroid_timer > 25 {
-        physics_world.sync(&mut physics_bodies);
+/// Random point just outside `edge` of the arena, biased towards the middle of that edge
+pub fn edge_position(arena: &Arena, rand: &mut StdRng, edge: Edge, radius: f64) -> (f64, f64) {
+    let (left, right, top, bottom) = arena.spawn_bounds();
 
-        game.asteroid_timer -= 25;
-
-        let left = -1300.0;
-        let right = 1300.0;
-        let top = -800.0;
-        let bottom = 800.0;
-
-        // Timer proc
-        let radius = rand.gen_range(40f64, 100f64);
-        let (x, y) =
-            // Align vertically
-            if rand::random() {(
-                // Left
-                if rand::random() {
-                    left - radius
-                }
-                // Right
-                else {
-                    right + radius
-                },
-                rand.gen_range(top / 2.0 - radius, bottom / 2.0 + radius),
-            )}
-            // Align horizontally
-            else {(
-                rand.gen_range(left / 2.0 - radius, right / 2.0 + radius),
-                // Top
-                if rand::random() {
-                    top - radius
-                }
-                // Bottom
-                else {
-                    bottom + radius
-                },
-            )};
+    let edge = match edge {
+        Edge::Random => match rand.gen_range(0, 4) {
+            0 => Edge::Left,
+            1 => Edge::Right,
+            2 => Edge::Top,
+            _ => Edge::Bottom,
+        },
+        edge => edge,
+    };
 
-        let transform = Transform::new(x as f64, y as f64);
-        let mut angle = transform.get_angle_to(0.0, 0.0);
-        angle += rand.gen_range(-22f64, 22f64);
-        let speed = rand.gen_range(5f64, 10f64);
+    match edge {
+        Edge::Left | Edge::Right => (
+            if edge == Edge::Left {
+                left - radius
+            } else {
+                right + radius
+            },
+            rand.gen_range(top / 2.0 - radius, bottom / 2.0 + radius),
+        ),
+        _ => (
+            rand.gen_range(left / 2.0 - radius, right / 2.0 + radius),
+            if edge == Edge::Top {
+                top - radius
+            } else {
+                bottom + radius
+            },
+        ),
+    }
+}
 
-        let asteroid = entities.add_entity(
-            (
-                &mut physicses,
-                &mut sprites,
-                &mut asteroids,
-            ),
-            (
-                Physics {
-                    speed,
-                    angle,
-                    ..Physics::default()
-                },
-                create_sprite(drawables.alias[textures::ASTEROID], radius, Color::rgb(0.3, 0.3, 0.3), draw_layers::ASTEROID),
-                Asteroid {},
+/// Spawns the archetype called `name` just outside `edge` of the arena, laid out in `formation`
+pub fn spawn_formation(all_storages: &AllStorages, name: &str, edge: Edge, formation: Formation) {
+    let archetype = match all_storages.borrow::<UniqueView<Archetypes>>().get(name) {
+        Some(archetype) => archetype.clone(),
+        None => {
+            eprintln!("No archetype called {}", name);
+            return;
+        }
+    };
 
-            ),
-        );
+    // Rolled before the position so it starts just off screen. The whole formation shares it.
+    let (x, y, radius, along_y) = all_storages.run(|arena: UniqueView<Arena>, mut rand: UniqueViewMut<StdRng>| {
+        let radius = archetype.roll_radius(&mut rand);
+        let (x, y) = edge_position(&arena, &mut rand, edge, radius);
+        // Formations spread along the edge the leader spawned on
+        (x, y, radius, x.abs() > arena.spawn_bounds().1)
+    });
 
-        physics_world.create_body(
-            &mut entities, 
-            &mut physics_bodies, 
-            asteroid,
-            &mut transforms,
-            transform,
-            CollisionBody::from_sensor(Collider::circle(
-                radius,
-                layers::ASTEROID,
-                layers::BULLET_PLAYER,
-            )),
-        );
+    match formation {
+        Formation::Single => {
+            spawn_archetype(all_storages, &archetype, x, y, radius);
+        }
+        Formation::Line { size, spacing } => {
+            let first = -(size.saturating_sub(1) as f64) * spacing / 2.0;
+            for i in 0..size {
+                let offset = first + i as f64 * spacing;
+                if along_y {
+                    spawn_archetype(all_storages, &archetype, x, y + offset, radius);
+                } else {
+                    spawn_archetype(all_storages, &archetype, x + offset, y, radius);
+                }
+            }
+        }
     }
 }
 
-pub fn spawn_spinners(
+pub fn run_director(
     all_storages: AllStoragesViewMut,
 ) {
-    while all_storages.run(|
-        mut game: UniqueViewMut<AsteroidGame>, 
-        mut physics_world: UniqueViewMut<PhysicsWorld>, 
-        mut physics_bodies: ViewMut<PhysicsBody>| {
-        game.spinner_timer += 1;
-        if game.spinner_timer > 400 {
-            physics_world.sync(&mut physics_bodies);
-            game.spinner_timer -= 400;
-            return true;
-        }
-        false
-    }) {
-        all_storages.run(|
-            drawables: NonSendSync<UniqueView<Drawables>>,
-            mut entities: EntitiesViewMut,
-            mut rand: UniqueViewMut<StdRng>,
-            players: View<Player>,
-            mut physicses: ViewMut<Physics>,
-            mut spinners: ViewMut<Spinner>,
-            mut sprites: ViewMut<Sprite>,
-            mut physics_bodies: ViewMut<PhysicsBody>,
-            mut physics_world: UniqueViewMut<PhysicsWorld>,
-            mut transforms: ViewMut<Transform>,| {
-                let left = -1300.0;
-                let right = 1300.0;
-                let top = -800.0;
-                let bottom = 800.0;
-                // Timer proc
-                let radius = 20f64;
-                let (x, y) =
-                    // Align vertically
-                    if rand::random() {(
-                        // Left
-                        if rand::random() {
-                            left - radius
-                        }
-                        // Right
-                        else {
-                            right + radius
-                        },
-                        rand.gen_range(top / 2.0 - radius, bottom / 2.0 + radius),
-                    )}
-                    // Align horizontally
-                    else {(
-                        rand.gen_range(left / 2.0 - radius, right / 2.0 + radius),
-                        // Top
-                        if rand::random() {
-                            top - radius
-                        }
-                        // Bottom
-                        else {
-                            bottom + radius
-                        },
-                    )};
-
-                let body = match (&physics_bodies, &players).iter().with_id().next() {
-                    Some((id, _)) => id,
-                    _ => return,
-                };
-                let player = physics_world.transform(body);
-
-                let transform = Transform::new(x as f64, y as f64);
-                let angle = transform.get_angle_to(player.x, player.y);
-                let spinner = entities.add_entity(
-                    (
-                        &mut spinners,
-                        &mut physicses,
-                        &mut sprites,
-                    ),
-                    (
-                        Spinner { angle, cooldown: 0 },
-                        Physics {
-                            accel: 0.18f64,
-                            angle,
-                            ..Physics::default()
-                        },
-                        create_sprite(drawables.alias[textures::ASTEROID], radius, Color::rgb(0.7, 0.0, 0.0), draw_layers::ENEMY),
-                    ),
-                );
-
-                physics_world.create_body(
-                    &mut entities, 
-                    &mut physics_bodies, 
-                    spinner, 
-                    &mut transforms,
-                    transform,
-                    CollisionBody::from_sensor(Collider::circle(radius, layers::ENEMY, 0)),
-            )
-        })
+    let orders = all_storages.run(|
+        mut director: UniqueViewMut<Director>,
+        asteroids: View<Asteroid>,
+        enemies: View<Enemy>,| {
+            let alive = asteroids.iter().count() + enemies.iter().count();
+            director.tick(alive)
+    });
+
+    for order in orders.into_iter() {
+        spawn_formation(&all_storages, &order.archetype, order.edge, order.formation);
     }
 }
 
-pub fn shoot_spinners(
-    drawables: NonSendSync<UniqueView<Drawables>>,
+pub fn run_emitters(
+    textures: UniqueView<SpriteTextures>,
     mut entities: EntitiesViewMut,
-    mut spinners: ViewMut<Spinner>,
+    players: View<Player>,
+    mut emitters: ViewMut<BulletEmitter>,
     mut bullets: ViewMut<Bullet>,
     mut physicses: ViewMut<Physics>,
     mut sprites: ViewMut<Sprite>,
@@ -358,34 +288,42 @@ pub fn shoot_spinners(
     mut transforms: ViewMut<Transform>,
 ) {
     let mut deferred = vec![];
+    let mut shots = vec![];
     physics_world.sync(&mut physics_bodies);
 
-    for (id, (_, spinner)) in (&physics_bodies, &mut spinners).iter().with_id() {
-        let transform = physics_world.transform(id);
+    let player = (&physics_bodies, &players)
+        .iter()
+        .with_id()
+        .next()
+        .map(|(id, _)| *physics_world.transform(id));
 
-        if spinner.cooldown > 0 {
-            spinner.cooldown -= 1;
-        } else {
-            for i in 0..4 {
-                spinner.cooldown = 4;
+    for (id, (_, emitter)) in (&physics_bodies, &mut emitters).iter().with_id() {
+        let transform = physics_world.transform(id);
+        let aim = player.map(|player| transform.get_angle_to(player.x, player.y));
 
-                spinner.angle += 4f64;
+        emitter.step(aim, &mut shots);
 
-                deferred.push(((
-                    Bullet::new(Team::Ast),
-                    Physics {
-                        speed: 3f64,
-                        angle: spinner.angle + i as f64 * 90f64,
-                        ..Physics::default()
-                    },
-                    create_sprite(drawables.alias[textures::ASTEROID], 7.5, Color::rgb(0.8, 0.0, 0.0), draw_layers::BULLET),
-                ), (
-                    Transform {
-                        ..*transform
-                    },
-                    CollisionBody::from_sensor(Collider::circle(7.5, layers::BULLET_ENEMY, layers::WALL)),
-                ),));
-            }
+        for (angle, shot) in shots.drain(..) {
+            let collider = Collider::circle(shot.radius, layers::BULLET_ENEMY, layers::WALL);
+            let bullet = Bullet::new(Team::Ast, shot.projectile);
+            let bounces = bullet.bounces();
+            deferred.push(((
+                bullet,
+                Physics {
+                    apply_auto: !bounces,
+                    speed: shot.speed,
+                    angle,
+                    accel: shot.accel,
+                    curve: shot.curve,
+                    ..Physics::default()
+                },
+                create_sprite(textures.asteroid, shot.radius, rgb(shot.color), draw_layers::BULLET),
+            ), (
+                Transform {
+                    ..*transform
+                },
+                if bounces { CollisionBody::from_collider(collider) } else { CollisionBody::from_sensor(collider) },
+            ),));
         }
     }
 
@@ -410,6 +348,10 @@ pub fn shoot_spinners(
     }
 }
 
+pub fn survival_timer(mut score: UniqueViewMut<Score>) {
+    score.frames_survived += 1;
+}
+
 pub fn iframe_counter(mut healths: ViewMut<Health>) {
     for health in (&mut healths)
         .iter()
@@ -423,7 +365,7 @@ pub fn player_input(
     all_storages: AllStoragesViewMut,
 ) {
     let (
-        drawables,
+        textures,
         mut entities,
         mut rand,
         mut physics_world,
@@ -434,7 +376,7 @@ pub fn player_input(
         mut bullets,
         mut transforms,
     ) = all_storages.borrow::<(
-        NonSendSync<UniqueView<Drawables>>,
+        UniqueView<SpriteTextures>,
         EntitiesViewMut,
         UniqueViewMut<StdRng>,
         UniqueViewMut<PhysicsWorld>,
@@ -447,6 +389,7 @@ pub fn player_input(
     )>();
 
     let game = all_storages.borrow::<UniqueView<AsteroidGame>>();
+    let effects = all_storages.borrow::<View<TimedEffects>>();
 
     physics_world.sync(&mut physics_bodies);
     
@@ -455,20 +398,42 @@ pub fn player_input(
         _ => return,
     };
 
-    let speed = 5f64;
-    let mut input = Vec2::new(0.0, 0.0);
-    if game.move_left {
-        input.x -= speed;
-    }
-    if game.move_right {
-        input.x += speed;
-    }
-    if game.move_up {
-        input.y -= speed;
-    }
-    if game.move_down {
-        input.y += speed;
-    }
+    let (speed_boost, extra_bounces) = match (&effects, &players).iter().next() {
+        Some((effects, _)) => (
+            effects.has(PowerUpKind::SpeedBoost),
+            if effects.has(PowerUpKind::ExtraBounce) { powerups::EXTRA_BOUNCES } else { 0 },
+        ),
+        _ => (false, 0),
+    };
+
+    let speed_scale = if speed_boost { powerups::SPEED_BOOST } else { 1.0 };
+    let wish = match game.move_axis {
+        Some((x, y)) => Vec2::new(x, y),
+        None => {
+            let mut wish = Vec2::new(0.0, 0.0);
+            if game.move_left {
+                wish.x -= 1.0;
+            }
+            if game.move_right {
+                wish.x += 1.0;
+            }
+            if game.move_up {
+                wish.y -= 1.0;
+            }
+            if game.move_down {
+                wish.y += 1.0;
+            }
+            wish
+        }
+    };
+
+    let input = {
+        let mut movements = all_storages.borrow::<ViewMut<PlayerMovement>>();
+        match (&mut movements, &players).iter().next() {
+            Some((movement, _)) => movement.update(wish, speed_scale),
+            _ => return,
+        }
+    };
 
     if input != Vec2::zero() {
         physics_world.move_body_and_collide(body, input);
@@ -476,209 +441,384 @@ pub fn player_input(
     let transform = physics_world.transform(body);
 
     let transform = transform.clone();
-    if game.lmb_down {        
-        let bullet = entities.add_entity(
-            (
-                &mut physicses,
-                &mut sprites,
-                &mut bullets,
-            ),
-            (
-                Physics {
-                    apply_auto: false,
-                    speed: 15.0,
-                    accel: 0.0,
-                    angle: game.shoot_angle,
-                    ..Physics::default()
-                },
-                create_sprite(drawables.alias[textures::ASTEROID], 20.0, Color::rgb(0.02, 0.24, 0.81), draw_layers::BULLET),
-                Bullet::new(Team::Player),
-            ),
-        );
 
-        let offset_col = rand.gen_range(-2, 2) * 15;
-        let offset_minor = rand.gen_range(-1, 1) * 8;
-        let offset_height = rand.gen_range(-1, 1) * 8;
-        let mut pos = Vec2::new(<f64>::sin(game.shoot_angle.to_radians()), -<f64>::cos(game.shoot_angle.to_radians()));
-        pos *= offset_height as f64 + 30.0;
+    let mut weapons = all_storages.borrow::<ViewMut<Weapon>>();
+    let weapon = match (&mut weapons, &players).iter().next() {
+        Some((weapon, _)) => weapon,
+        _ => return,
+    };
 
-        let mut left = Vec2::new(pos.y, -pos.x);
-        if left != Vec2::zero() {
-            left.normalize();
-        }
-        left *= (offset_col + offset_minor) as f64;
+    if weapon.cooldown > 0 {
+        weapon.cooldown -= 1;
+    }
+
+    if game.lmb_down && weapon.cooldown == 0 {
+        weapon.cooldown = weapon.fire_interval;
+        all_storages.borrow::<UniqueViewMut<Events>>().emit(GameEvent::ShotFired {
+            x: transform.x,
+            y: transform.y,
+            count: weapon.projectile_count,
+        });
+
+        for i in 0..weapon.projectile_count {
+            // Fan projectiles out evenly across the spread
+            let angle = if weapon.projectile_count > 1 {
+                game.shoot_angle - weapon.spread / 2.0 + weapon.spread * i as f64 / (weapon.projectile_count - 1) as f64
+            } else {
+                game.shoot_angle
+            };
+
+            let bullet = Bullet::new(Team::Player, weapon.projectile).with_extra_bounces(extra_bounces);
+            let collider = Collider::circle(weapon.projectile_radius, layers::BULLET_PLAYER, layers::WALL);
+            let collision_body = if bullet.bounces() {
+                CollisionBody::from_collider(collider)
+            } else {
+                CollisionBody::from_sensor(collider)
+            };
+
+            let bullet = entities.add_entity(
+                (
+                    &mut physicses,
+                    &mut sprites,
+                    &mut bullets,
+                ),
+                (
+                    Physics {
+                        apply_auto: !bullet.bounces(),
+                        speed: weapon.projectile_speed,
+                        accel: 0.0,
+                        angle,
+                        ..Physics::default()
+                    },
+                    create_sprite(textures.asteroid, weapon.projectile_radius, Color::rgb(0.02, 0.24, 0.81), draw_layers::BULLET),
+                    bullet,
+                ),
+            );
 
-        pos += left;
+            let offset_col = rand.gen_range(-2, 2) * 15;
+            let offset_minor = rand.gen_range(-1, 1) * 8;
+            let offset_height = rand.gen_range(-1, 1) * 8;
+            let mut pos = Vec2::new(<f64>::sin(angle.to_radians()), -<f64>::cos(angle.to_radians()));
+            pos *= offset_height as f64 + 30.0;
 
-        pos.x += transform.x;
-        pos.y += transform.y;
+            let mut left = Vec2::new(pos.y, -pos.x);
+            if left != Vec2::zero() {
+                left.normalize();
+            }
+            left *= (offset_col + offset_minor) as f64;
 
-        physics_world.create_body(
-            &mut entities, 
-            &mut physics_bodies, 
-            bullet, 
-            &mut transforms,
-            Transform {
-                x: pos.x,
-                y: pos.y,
-                ..Transform::default()
-            },
-            CollisionBody::from_collider(
-                Collider::circle(
-                    20.0,
-                    layers::BULLET_PLAYER,
-                    layers::WALL
-                ),),
-        );
+            pos += left;
+
+            pos.x += transform.x;
+            pos.y += transform.y;
+
+            physics_world.create_body(
+                &mut entities, 
+                &mut physics_bodies, 
+                bullet, 
+                &mut transforms,
+                Transform {
+                    x: pos.x,
+                    y: pos.y,
+                    ..Transform::default()
+                },
+                collision_body,
+            );
+        }
     }
 }
 
-pub fn player_damage(mut all_storages: AllStoragesViewMut) {
+/// Turns sensor overlaps into `Damage` for anything with `Health`, following `DAMAGE_RULES`
+pub fn collect_damage(mut all_storages: AllStoragesViewMut) {
     let mut kill = vec![];
 
     {
-        let (mut collision_bodies, mut healths, players, mut sprites, mut physics_world) =
-            all_storages.borrow::<(ViewMut<PhysicsBody>, ViewMut<Health>, View<Player>, ViewMut<Sprite>, UniqueViewMut<PhysicsWorld>)>();
+        let (mut queue, mut events, mut physics_bodies, healths, effects, mut bullets, mut physics_world) = all_storages
+            .borrow::<(
+                UniqueViewMut<DamageQueue>,
+                UniqueViewMut<Events>,
+                ViewMut<PhysicsBody>,
+                View<Health>,
+                View<TimedEffects>,
+                ViewMut<Bullet>,
+                UniqueViewMut<PhysicsWorld>,
+            )>();
 
-        physics_world.sync(&mut collision_bodies);
+        physics_world.sync(&mut physics_bodies);
 
-        let (id, body, health, sprite) = match (&mut collision_bodies, &players, &mut healths, &mut sprites)
+        let shielded: Vec<EntityId> = effects
             .iter()
             .with_id()
-            .next()
-        {
-            Some((id, (_, _, hp, sprite))) => (id, physics_world.collider(id), hp, sprite),
-            _ => return,
-        };
+            .filter(|(_, effects)| effects.has(PowerUpKind::Shield))
+            .map(|(id, _)| id)
+            .collect();
 
-        if health.iframe_count > 0 {
-            return;
-        } else {
-            sprite.0.color = Color::rgb(0.0, 1.0, 0.0);
+        for (id, (_, health)) in (&physics_bodies, &healths).iter().with_id() {
+            if health.hp <= 0 || shielded.contains(&id) {
+                continue;
+            }
+
+            let body = physics_world.collider(id);
+            let sensor = match body.sensors.get(0) {
+                Some(sensor) => sensor,
+                _ => continue,
+            };
+
+            for collision in sensor.overlapping.iter() {
+                let rule = match find_rule(collision.collision_layer1, collision.collision_layer2) {
+                    Some(rule) => rule,
+                    _ => continue,
+                };
+
+                if rule.consumes_source {
+                    if rule.passes_iframes && health.iframe_count > 0 {
+                        continue;
+                    }
+
+                    let first = (&bullets).iter().with_id().find(|(e, _)| *e == collision.entity2);
+                    if let Some((_, bullet)) = first.filter(|(_, bullet)| bullet.hits.is_empty()) {
+                        events.emit(GameEvent::BulletLanded {
+                            x: collision.transform2.x,
+                            y: collision.transform2.y,
+                            team: bullet.team,
+                        });
+                    }
+
+                    match bullet_hit(&mut bullets, collision.entity2, id) {
+                        Some(true) => kill.push(collision.entity2),
+                        Some(false) => { }
+                        None => continue,
+                    }
+                }
+
+                queue.damage.push(Damage {
+                    target: id,
+                    source: collision.entity2,
+                    from: (collision.transform2.x, collision.transform2.y),
+                    amount: rule.amount,
+                });
+            }
         }
+    }
 
-        for collision in body.sensors[0].overlapping.iter() {
-            if collision.collision_layer2 & layers::ASTEROID > 0 {
-                health.hp -= 1;
-                health.iframe_count = health.iframe_max;
-                sprite.0.color = Color::RED;
-                break;
-            } else if collision.collision_layer2 & layers::ENEMY > 0 {
-                health.hp -= 1;
-                health.iframe_count = health.iframe_max;
-                sprite.0.color = Color::RED;
-                break;
-            } else if collision.collision_layer2 & layers::BULLET_ENEMY > 0 {
-                health.hp -= 1;
-                health.iframe_count = health.iframe_max;
-                sprite.0.color = Color::RED;
-                kill.push(collision.entity2);
-                break;
+    for id in kill.into_iter() {
+        all_storages.delete(id);
+    }
+}
+
+/// Applies queued `Damage`, honouring iframes, and queues a `Death` for anything that runs out of health
+pub fn apply_damage(
+    mut queue: UniqueViewMut<DamageQueue>,
+    mut events: UniqueViewMut<Events>,
+    players: View<Player>,
+    transforms: View<Transform>,
+    mut healths: ViewMut<Health>,
+    mut sprites: ViewMut<Sprite>,
+) {
+    let DamageQueue { damage, deaths } = &mut *queue;
+
+    for (id, (health, sprite, transform)) in (&mut healths, &mut sprites, &transforms).iter().with_id() {
+        for hit in damage.iter().filter(|hit| hit.target == id) {
+            if health.hp <= 0 || health.iframe_count > 0 {
+                continue;
+            }
+
+            health.hp -= hit.amount;
+            health.iframe_count = health.iframe_max;
+            if let Some(col) = health.iframe_col {
+                sprite.0.color = col;
+            }
+
+            if players.iter().with_id().any(|(e, _)| e == id) {
+                events.emit(GameEvent::PlayerHit { x: transform.x, y: transform.y, hp: health.hp });
+            }
+
+            if health.hp <= 0 {
+                deaths.push(Death { entity: id, from: hit.from });
             }
         }
+    }
+
+    damage.clear();
+}
 
-        if health.hp <= 0 {
-            kill.push(id.clone());
+/// Puts back the usual colour of anything that finished flashing from a hit
+pub fn restore_tints(
+    healths: View<Health>,
+    enemies: View<Enemy>,
+    players: View<Player>,
+    effects: View<TimedEffects>,
+    mut sprites: ViewMut<Sprite>,
+) {
+    for (health, enemy, sprite) in (&healths, &enemies, &mut sprites).iter() {
+        if health.iframe_count == 0 {
+            sprite.0.color = enemy.color;
         }
     }
 
-    for id in kill.into_iter() {
-        all_storages.delete(id);
+    for (health, _, effects, sprite) in (&healths, &players, &effects, &mut sprites).iter() {
+        if health.iframe_count > 0 {
+            continue;
+        }
+
+        sprite.0.color = if effects.has(PowerUpKind::Shield) {
+            PowerUpKind::Shield.color()
+        } else {
+            Color::rgb(0.0, 1.0, 0.0)
+        };
     }
 }
 
-pub fn asteroid_damage(mut all_storages: AllStoragesViewMut) {
-    let mut create = vec![];
+/// Splits, bursts and drops for everything that died this frame, then removes it
+pub fn handle_deaths(mut all_storages: AllStoragesViewMut) {
+    let mut splits = vec![];
+    let mut bursts = vec![];
+    let mut drops = vec![];
     let mut kill = vec![];
 
     {
-        let (mut rand, mut physics_bodies, asteroids, mut sprites, mut physicses, mut physics_world) = all_storages
+        let (mut queue, mut events, mut rand, mut physics_bodies, on_deaths, asteroids, enemies, healths, sprites, physicses, mut physics_world) = all_storages
             .borrow::<(
+                UniqueViewMut<DamageQueue>,
+                UniqueViewMut<Events>,
                 UniqueViewMut<StdRng>,
                 ViewMut<PhysicsBody>,
+                View<OnDeath>,
                 View<Asteroid>,
-                ViewMut<Sprite>,
-                ViewMut<Physics>,
+                View<Enemy>,
+                View<Health>,
+                View<Sprite>,
+                View<Physics>,
                 UniqueViewMut<PhysicsWorld>,
             )>();
 
-        physics_world.sync(&mut physics_bodies);
-        
-
-        for (id, (_, physics, _, sprite)) in
-            (&mut physics_bodies, &mut physicses, &asteroids, &mut sprites)
-                .iter()
-                .with_id()
-        {
-            let (transform, body) = physics_world.parts_mut(id);
+        if queue.deaths.is_empty() {
+            return;
+        }
 
-            let overlapping = &mut body.sensors[0].overlapping;
-            for collision in overlapping.clone().iter() {
-                if collision.collision_layer2 & layers::BULLET_PLAYER > 0 {
-                    kill.push(collision.entity2);
+        physics_world.sync(&mut physics_bodies);
 
-                    sprite.0.scale /= 1.5;
+        for death in queue.deaths.iter() {
+            let transform = physics_world.transform(death.entity);
+            events.emit(GameEvent::EntityDied { entity: death.entity, x: transform.x, y: transform.y });
+        }
 
-                    match body.sensors[0].shape {
-                        CollisionShape::Circle(r) => {
-                            body.sensors[0].shape = CollisionShape::Circle(r / 1.5)
-                        }
-                        _ => { }
+        for (id, (_, on_death, health, sprite, physics)) in (&physics_bodies, &on_deaths, &healths, &sprites, &physicses).iter().with_id() {
+            let death = match queue.deaths.iter().find(|death| death.entity == id) {
+                Some(death) => *death,
+                _ => continue,
+            };
+
+            let transform = *physics_world.transform(id);
+            let body = physics_world.collider(id);
+            let radius = body.sensors[0].shape.get_width() / 2.0;
+            let asteroid = (&asteroids).iter().with_id().any(|(e, _)| e == id);
+            let enemy = (&enemies).iter().with_id().find(|(e, _)| *e == id).map(|(_, enemy)| *enemy);
+
+            match *on_death {
+                OnDeath::Split { factor, min_radius } if radius / factor >= min_radius => {
+                    if asteroid {
+                        events.emit(GameEvent::AsteroidSplit { x: transform.x, y: transform.y, radius });
                     }
 
-                    if body.sensors[0].shape.get_width() / 2.0 < 15f64 {
-                        kill.push(id);
-                    } else {
-                        let mut new_physics = Physics { ..*physics };
-                        let angle = collision
-                            .transform2
-                            .get_angle_to(collision.transform1.x, collision.transform1.y);
-                        new_physics.angle = angle + rand.gen_range(0f64, 140f64);
-                        physics.angle = angle + rand.gen_range(0f64, 140f64);
-
-                        let collision_body = CollisionBody::from_body(&body);
+                    let angle = transform.get_angle_to(death.from.0, death.from.1) + 180.0;
+                    for _ in 0..2 {
+                        let mut collision_body = CollisionBody::from_body(body);
+                        match collision_body.sensors[0].shape {
+                            CollisionShape::Circle(r) => {
+                                collision_body.sensors[0].shape = CollisionShape::Circle(r / factor)
+                            }
+                            _ => { }
+                        }
 
-                        create.push((*transform, new_physics, collision_body));
+                        let physics = Physics {
+                            angle: angle + rand.gen_range(0f64, 140f64),
+                            ..*physics
+                        };
+
+                        splits.push((
+                            transform,
+                            physics,
+                            collision_body,
+                            radius / factor,
+                            sprite.0.color,
+                            Health::new(health.max, health.iframe_max, health.iframe_col),
+                            *on_death,
+                            asteroid,
+                            enemy,
+                        ));
                     }
                 }
+                OnDeath::Split { .. } => {
+                    if asteroid {
+                        events.emit(GameEvent::AsteroidDestroyed { x: transform.x, y: transform.y, radius });
+                        drops.push((transform.x, transform.y, powerups::DROP_CHANCE_ASTEROID));
+                    }
+                }
+                OnDeath::Burst => bursts.push((transform, sprite.0.scale)),
+                OnDeath::Vanish => { }
+            }
+
+            if let Some(enemy) = enemy {
+                events.emit(GameEvent::EnemyDestroyed { x: transform.x, y: transform.y, score: enemy.score });
+                drops.push((transform.x, transform.y, powerups::DROP_CHANCE_ENEMY));
             }
         }
+
+        // Things without an `OnDeath`, like the player, are just removed
+        kill.extend(queue.deaths.drain(..).map(|death| death.entity));
     }
 
     {
         let (
-            drawables,
+            textures,
             mut entities,
             mut transforms,
             mut asteroids,
+            mut enemies,
+            mut healths,
+            mut on_deaths,
             mut sprites,
             mut physicses,
             mut physics_bodies,
             mut physics_world,
         ) = all_storages.borrow::<(
-            NonSendSync<UniqueView<Drawables>>,
+            UniqueView<SpriteTextures>,
             EntitiesViewMut,
             ViewMut<Transform>,
             ViewMut<Asteroid>,
+            ViewMut<Enemy>,
+            ViewMut<Health>,
+            ViewMut<OnDeath>,
             ViewMut<Sprite>,
             ViewMut<Physics>,
             ViewMut<PhysicsBody>,
             UniqueViewMut<PhysicsWorld>,
         )>();
-        for (transform, physics, collision_body) in create.into_iter() {
+
+        for (transform, physics, collision_body, radius, color, health, on_death, asteroid, enemy) in splits.into_iter() {
             let splitted = entities.add_entity(
                 (
-                    &mut asteroids,
                     &mut sprites,
                     &mut physicses,
+                    &mut healths,
+                    &mut on_deaths,
                 ),
                 (
-                    Asteroid {},
-                    create_sprite(drawables.alias[textures::ASTEROID], collision_body.sensors[0].shape.get_width() / 2.0, Color::rgb(0.3, 0.3, 0.3), draw_layers::ASTEROID),
+                    create_sprite(textures.asteroid, radius, color, if asteroid { draw_layers::ASTEROID } else { draw_layers::ENEMY }),
                     physics,
+                    health,
+                    on_death,
                 ),
             );
 
+            if asteroid {
+                entities.add_component(&mut asteroids, Asteroid {}, splitted);
+            }
+            if let Some(enemy) = enemy {
+                entities.add_component(&mut enemies, enemy, splitted);
+            }
+
             physics_world.create_body(
                 &mut entities,
                 &mut physics_bodies,
@@ -690,6 +830,189 @@ pub fn asteroid_damage(mut all_storages: AllStoragesViewMut) {
         }
     }
 
+    {
+        let (textures, mut entities, mut transforms, mut sprites, mut fades) = all_storages
+            .borrow::<(
+                UniqueView<SpriteTextures>,
+                EntitiesViewMut,
+                ViewMut<Transform>,
+                ViewMut<Sprite>,
+                ViewMut<Fade>,
+            )>();
+
+        for (transform, scale) in bursts.into_iter() {
+            // Grows and fades out where it died
+            let mut sprite = create_sprite(textures.asteroid, 20.0, Color::rgb(1.0, 0.6, 0.2), draw_layers::ENEMY);
+            sprite.0.scale = scale;
+            entities.add_entity(
+                (
+                    &mut transforms,
+                    &mut sprites,
+                    &mut fades,
+                ),
+                (
+                    transform,
+                    sprite,
+                    Fade::new(20, 1.08),
+                ),
+            );
+        }
+    }
+
+    for (x, y, chance) in drops.into_iter() {
+        maybe_drop_powerup(&all_storages, x, y, chance);
+    }
+
+    for id in kill.into_iter() {
+        all_storages.delete(id);
+    }
+}
+
+pub fn score_events(events: UniqueView<Events>, mut score: UniqueViewMut<Score>) {
+    for event in events.iter() {
+        match *event {
+            GameEvent::AsteroidSplit { radius, .. } => score.add_asteroid_split(radius),
+            GameEvent::AsteroidDestroyed { radius, .. } => score.add_asteroid_destroyed(radius),
+            GameEvent::EnemyDestroyed { score: points, .. } => score.add_enemy_destroyed(points),
+            _ => { }
+        }
+    }
+}
+
+/// `Bullet::hit` for the bullet entity `bullet`, `Some(true)` if it isn't a bullet at all
+fn bullet_hit(bullets: &mut ViewMut<Bullet>, bullet: EntityId, target: EntityId) -> Option<bool> {
+    match (&mut *bullets).iter().with_id().find(|(id, _)| *id == bullet) {
+        Some((_, bullet)) => bullet.hit(target),
+        None => Some(true),
+    }
+}
+
+/// Rolls `chance` to leave a random power-up at (`x`, `y`)
+pub fn maybe_drop_powerup(all_storages: &AllStorages, x: f64, y: f64, chance: f64) {
+    all_storages.run(|
+        textures: UniqueView<SpriteTextures>,
+        mut entities: EntitiesViewMut,
+        mut rand: UniqueViewMut<StdRng>,
+        mut powerup_tags: ViewMut<PowerUp>,
+        mut sprites: ViewMut<Sprite>,
+        mut fades: ViewMut<Fade>,
+        mut physics_bodies: ViewMut<PhysicsBody>,
+        mut physics_world: UniqueViewMut<PhysicsWorld>,
+        mut transforms: ViewMut<Transform>,| {
+            if !rand.gen_bool(chance) {
+                return;
+            }
+
+            let kind = PowerUpKind::ALL[rand.gen_range(0, PowerUpKind::ALL.len())];
+            let powerup = entities.add_entity(
+                (
+                    &mut powerup_tags,
+                    &mut sprites,
+                    &mut fades,
+                ),
+                (
+                    PowerUp { kind },
+                    create_sprite(textures.square, powerups::RADIUS, kind.color(), draw_layers::POWERUP),
+                    // Fades out over its lifetime and is deleted once it expires
+                    Fade::new(powerups::LIFETIME, 1.0),
+                ),
+            );
+
+            physics_world.create_body(
+                &mut entities,
+                &mut physics_bodies,
+                powerup,
+                &mut transforms,
+                Transform::new(x, y),
+                CollisionBody::from_sensor(Collider::circle(powerups::RADIUS, layers::POWERUP, 0)),
+            );
+    });
+}
+
+pub fn collect_powerups(mut all_storages: AllStoragesViewMut) {
+    let mut collected = vec![];
+
+    {
+        let (mut events, mut physics_bodies, players, powerup_tags, mut effects, mut healths, mut weapons, mut physics_world) = all_storages
+            .borrow::<(
+                UniqueViewMut<Events>,
+                ViewMut<PhysicsBody>,
+                View<Player>,
+                View<PowerUp>,
+                ViewMut<TimedEffects>,
+                ViewMut<Health>,
+                ViewMut<Weapon>,
+                UniqueViewMut<PhysicsWorld>,
+            )>();
+
+        physics_world.sync(&mut physics_bodies);
+
+        let (id, effects, health, weapon) = match (&players, &mut effects, &mut healths, &mut weapons).iter().with_id().next() {
+            Some((id, (_, effects, health, weapon))) => (id, effects, health, weapon),
+            _ => return,
+        };
+
+        let body = physics_world.collider(id);
+        for collision in body.sensors[0].overlapping.iter() {
+            if collision.collision_layer2 & layers::POWERUP == 0 || collected.contains(&collision.entity2) {
+                continue;
+            }
+
+            let kind = match (&powerup_tags).iter().with_id().find(|(e, _)| *e == collision.entity2) {
+                Some((_, powerup)) => powerup.kind,
+                _ => continue,
+            };
+            collected.push(collision.entity2);
+            events.emit(GameEvent::PowerUpCollected { kind, x: collision.transform2.x, y: collision.transform2.y });
+
+            match kind {
+                PowerUpKind::HealthRefill => health.hp = health.max,
+                PowerUpKind::SpreadShot => {
+                    if !effects.has(kind) {
+                        let cooldown = weapon.cooldown;
+                        *weapon = Weapon::scatter();
+                        weapon.cooldown = cooldown.min(weapon.fire_interval);
+                    }
+                    effects.add(kind, powerups::DURATION);
+                }
+                _ => effects.add(kind, powerups::DURATION),
+            }
+        }
+    }
+
+    for id in collected.into_iter() {
+        all_storages.delete(id);
+    }
+}
+
+pub fn tick_effects(mut effects: ViewMut<TimedEffects>, mut weapons: ViewMut<Weapon>) {
+    for (effects, weapon) in (&mut effects, &mut weapons).iter() {
+        for kind in effects.tick().into_iter() {
+            if kind == PowerUpKind::SpreadShot {
+                let cooldown = weapon.cooldown;
+                *weapon = Weapon::blaster();
+                weapon.cooldown = cooldown.min(weapon.fire_interval);
+            }
+        }
+    }
+}
+
+pub fn update_fades(mut all_storages: AllStoragesViewMut) {
+    let mut kill = vec![];
+
+    all_storages.run(|mut fades: ViewMut<Fade>, mut sprites: ViewMut<Sprite>| {
+        for (id, (fade, sprite)) in (&mut fades, &mut sprites).iter().with_id() {
+            fade.life -= 1;
+            if fade.life <= 0 {
+                kill.push(id);
+                continue;
+            }
+
+            sprite.0.scale *= fade.grow;
+            sprite.0.color.a = fade.life as f32 / fade.max_life as f32;
+        }
+    });
+
     for id in kill.into_iter() {
         all_storages.delete(id);
     }
@@ -699,6 +1022,7 @@ pub fn destroy_bullets(mut all_storages: AllStoragesViewMut) {
     let mut to_kill = vec![];
 
     all_storages.run(|
+        mut events: UniqueViewMut<Events>,
         bullets: View<Bullet>,
         mut bodies: ViewMut<PhysicsBody>,
         world: UniqueViewMut<PhysicsWorld>,| {
@@ -711,11 +1035,16 @@ pub fn destroy_bullets(mut all_storages: AllStoragesViewMut) {
                     }
                 }
 
-                if bullet.bounces >= bullet.bounce_limit {
+                if bullet.bounces() && bullet.bounces >= bullet.bounce_limit {
                     to_kill.push(id);
                     continue;
                 }
             }
+
+            for id in to_kill.iter() {
+                let transform = world.transform(*id);
+                events.emit(GameEvent::BulletDestroyed { x: transform.x, y: transform.y });
+            }
     });
 
     for id in to_kill.into_iter() {
@@ -723,9 +1052,41 @@ pub fn destroy_bullets(mut all_storages: AllStoragesViewMut) {
     }
 }
 
-pub fn move_camera(player: View<Player>, mut camera: UniqueViewMut<Camera>, physics_bodies: View<PhysicsBody>, physics_world: UniqueView<PhysicsWorld>) {
-    for (id, _) in (&player, &physics_bodies).iter().with_id() {
-        let t = physics_world.transform(id);
-        camera.position = Vec2::new(t.x as f32, t.y as f32);
+pub fn move_camera(
+    arena: UniqueView<Arena>,
+    events: UniqueView<Events>,
+    player: View<Player>,
+    mut controller: UniqueViewMut<CameraController>,
+    mut camera: UniqueViewMut<Camera>,
+    physics_bodies: View<PhysicsBody>,
+    physics_world: UniqueView<PhysicsWorld>,
+) {
+    for event in events.iter() {
+        match event {
+            GameEvent::PlayerHit { .. } => {
+                let trauma = controller.config.hit_trauma;
+                controller.add_trauma(trauma);
+            }
+            GameEvent::AsteroidDestroyed { .. } | GameEvent::EnemyDestroyed { .. } => {
+                let (trauma, pulse) = (controller.config.kill_trauma, controller.config.kill_zoom_pulse);
+                controller.add_trauma(trauma);
+                controller.pulse_zoom(pulse);
+            }
+            _ => { }
+        }
     }
-}
\ No newline at end of file
+
+    // Stays where it was once the player is gone
+    let target = match (&player, &physics_bodies).iter().with_id().next() {
+        Some((id, _)) => {
+            let t = physics_world.transform(id);
+            Vec2::new(t.x as f32, t.y as f32)
+        }
+        _ => controller.focus,
+    };
+
+    let viewport = Vec2::new(camera.viewport_width, camera.viewport_height);
+    let (position, zoom) = controller.update(target, &arena, viewport);
+    camera.position = position;
+    camera.zoom = zoom;
+}
@@ -0,0 +1,24 @@
+use std::{
+    fs,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+//
+// Helpers shared by the unit tests
+
+/// A path in the temp directory unique to `name` and this test run
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("asteroids-{}-{}", name, std::process::id()))
+}
+
+/// Writes `contents` to a temp file, runs `f` on its path and removes it again
+pub fn with_file<T, F: FnOnce(&Path) -> T>(name: &str, contents: &str, f: F) -> T {
+    let path = temp_path(name);
+    fs::write(&path, contents).unwrap();
+    let result = f(&path);
+    fs::remove_file(&path).unwrap();
+    result
+}
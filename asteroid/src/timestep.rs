@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+use vermarine_lib::{
+    shipyard::*,
+    tetra::{
+        graphics::Camera,
+        math::Vec2,
+    },
+    components::{
+        Transform,
+    },
+};
+
+/// Ticks per second the simulation runs at. Every speed, timer and cooldown in the game is per tick.
+pub const TICK_RATE: f64 = 60.0;
+
+/// More than this many ticks owed in one update are dropped rather than caught up on
+const MAX_STEPS: u32 = 8;
+
+//
+// Fixed timestep accumulator
+//
+// Turns real time passed into a whole number of simulation ticks, whatever rate the game is updated or drawn at
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    dt: Duration,
+    accumulator: Duration,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f64) -> Self {
+        FixedTimestep {
+            dt: Duration::from_secs_f64(1.0 / tick_rate),
+            accumulator: Duration::from_secs(0),
+            last: None,
+        }
+    }
+
+    /// How many ticks to run for the time passed since the last call. The first call always runs one.
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        let last = match self.last.replace(now) {
+            Some(last) => last,
+            None => return 1,
+        };
+
+        self.accumulator += now - last;
+
+        let mut steps = 0;
+        while self.accumulator >= self.dt {
+            self.accumulator -= self.dt;
+            steps += 1;
+        }
+
+        if steps > MAX_STEPS {
+            steps = MAX_STEPS;
+        }
+        steps
+    }
+
+    /// How far between the last tick and the next one we are right now, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        let since = match self.last {
+            Some(last) => self.accumulator + last.elapsed(),
+            None => return 1.0,
+        };
+        (since.as_secs_f64() / self.dt.as_secs_f64()).min(1.0) as f32
+    }
+
+    /// Forget about time passed so far, e.g. after the game was paused
+    pub fn reset(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+        self.last = None;
+    }
+}
+
+//
+// Render interpolation
+//
+// Positions from the start of the latest tick, so drawing can blend between them and the current ones
+#[derive(Clone, Debug, Default)]
+pub struct PreviousTransforms {
+    positions: HashMap<EntityId, (f64, f64)>,
+    camera: Vec2<f32>,
+}
+
+/// Call before running a tick
+pub fn snapshot_transforms(world: &World) {
+    world.run(|
+        transforms: View<Transform>,
+        camera: UniqueView<Camera>,
+        mut previous: UniqueViewMut<PreviousTransforms>,| {
+            previous.positions.clear();
+            for (id, transform) in transforms.iter().with_id() {
+                previous.positions.insert(id, (transform.x, transform.y));
+            }
+            previous.camera = camera.position;
+    });
+}
+
+/// Moves every transform and the camera `alpha` of the way from their previous position to their current one.
+/// Returns what's needed to put them back with `restore_transforms` once drawing is done.
+pub fn interpolate_transforms(world: &World, alpha: f32) -> (HashMap<EntityId, (f64, f64)>, Vec2<f32>) {
+    world.run(|
+        mut transforms: ViewMut<Transform>,
+        mut camera: UniqueViewMut<Camera>,
+        previous: UniqueView<PreviousTransforms>,| {
+            let alpha64 = alpha as f64;
+            let mut current = HashMap::new();
+
+            for (id, transform) in (&mut transforms).iter().with_id() {
+                // Things spawned this tick have nowhere to blend from
+                if let Some((x, y)) = previous.positions.get(&id) {
+                    current.insert(id, (transform.x, transform.y));
+                    transform.x = x + (transform.x - x) * alpha64;
+                    transform.y = y + (transform.y - y) * alpha64;
+                }
+            }
+
+            let camera_current = camera.position;
+            camera.position = previous.camera + (camera_current - previous.camera) * alpha;
+
+            (current, camera_current)
+    })
+}
+
+pub fn restore_transforms(world: &World, (current, camera_current): (HashMap<EntityId, (f64, f64)>, Vec2<f32>)) {
+    world.run(|mut transforms: ViewMut<Transform>, mut camera: UniqueViewMut<Camera>| {
+        for (id, transform) in (&mut transforms).iter().with_id() {
+            if let Some((x, y)) = current.get(&id) {
+                transform.x = *x;
+                transform.y = *y;
+            }
+        }
+        camera.position = camera_current;
+    });
+}